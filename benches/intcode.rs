@@ -0,0 +1,48 @@
+//! Compares the reference Intcode machine with the one compiling instructions into threaded code,
+//! on the workload of day 9's part 2 (run it with `cargo bench`).
+
+use std::time::{Duration, Instant};
+
+use aoc_2019::intcode::{self, FastVm, State, Vm};
+
+static INPUT: &str = include_str!("../inputs/day9.txt");
+
+const ITERATIONS: u32 = 50;
+
+fn bench(name: &str, mut run: impl FnMut() -> Vec<isize>) -> Duration {
+    // warm up, and make sure both machines agree.
+    let outputs = run();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert_eq!(run(), outputs);
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    println!(
+        "{:<10} {:>10.3?} per run (outputs: {:?})",
+        name, elapsed, outputs
+    );
+    elapsed
+}
+
+fn main() {
+    let program = intcode::parse(INPUT);
+
+    let naive = bench("naive", || {
+        let mut vm = Vm::new(program.clone());
+        vm.push_input(2);
+        assert_eq!(vm.run().expect("invalid program"), State::Halted);
+        vm.take_outputs()
+    });
+    let threaded = bench("threaded", || {
+        let mut vm = FastVm::new(program.clone());
+        vm.push_input(2);
+        assert_eq!(vm.run().expect("invalid program"), State::Halted);
+        vm.take_outputs()
+    });
+
+    println!(
+        "speedup: {:.2}x",
+        naive.as_secs_f64() / threaded.as_secs_f64()
+    );
+}
@@ -0,0 +1,13 @@
+#![no_main]
+
+use aoc_2019::intcode::{Backend, Case, Engine, FUZZ_FUEL};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let backends: [&dyn Backend; 2] = [&Engine::Sparse, &Engine::Fast];
+    let case = Case::generate(data);
+    if let Err(divergence) = case.check(&backends, FUZZ_FUEL) {
+        let reproducer = case.shrink(|case| case.check(&backends, FUZZ_FUEL).is_err());
+        panic!("{}\n{}", divergence, reproducer);
+    }
+});
@@ -10,11 +10,7 @@ pub enum Location {
 
 impl Location {
     pub fn is_empty(self) -> bool {
-        if let Location::Empty = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Location::Empty)
     }
 }
 
@@ -101,7 +101,7 @@ impl FromStr for Moon {
 fn find_cycle(mut values: Vec<(isize, isize)>) -> isize {
     let mut last = values.clone();
     let mut set = HashSet::new();
-    for steps in 0..std::isize::MAX {
+    for steps in 0..isize::MAX {
         let inserted = set.insert({
             let mut hasher = DefaultHasher::new();
             values.hash(&mut hasher);
@@ -206,6 +206,6 @@ fn main() {
             .collect(),
     );
 
-    let part2 = lcm(lcm(x_cycle, y_cycle), find_cycle(z_cycle));
+    let part2 = lcm(lcm(x_cycle, y_cycle), z_cycle);
     println!("{}", part2);
 }
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 pub static INPUT: &str = include_str!("../../inputs/day3.txt");
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Add, Sub, AddAssign, SubAssign)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Add, Sub, AddAssign, SubAssign)]
 pub struct Point(isize, isize);
 
 impl Point {
@@ -18,12 +18,6 @@ impl Point {
     }
 }
 
-impl Default for Point {
-    fn default() -> Point {
-        Point(0, 0)
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     Left,
@@ -108,7 +102,7 @@ fn main() {
     let part2 = visited
         .iter()
         .filter(|(_, (v1, v2))| v1.is_some() && v2.is_some())
-        .flat_map(|(_, &(v1, v2))| v1.into_iter().zip(v2.into_iter()))
+        .flat_map(|(_, &(v1, v2))| v1.into_iter().zip(v2))
         .map(|(v1, v2)| v1 + v2)
         .min()
         .expect("found no intersections");
@@ -1,199 +1,15 @@
-use std::convert::TryFrom;
 use std::io;
 
-pub static INPUT: &str = include_str!("../../inputs/day5.txt");
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Opcode {
-    Add,
-    Multiply,
-    Input,
-    Output,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    EqualTo,
-    Halt,
-}
-
-impl TryFrom<usize> for Opcode {
-    type Error = ();
-    fn try_from(num: usize) -> Result<Opcode, Self::Error> {
-        match num {
-            1 => Ok(Opcode::Add),
-            2 => Ok(Opcode::Multiply),
-            3 => Ok(Opcode::Input),
-            4 => Ok(Opcode::Output),
-            5 => Ok(Opcode::JumpIfTrue),
-            6 => Ok(Opcode::JumpIfFalse),
-            7 => Ok(Opcode::LessThan),
-            8 => Ok(Opcode::EqualTo),
-            99 => Ok(Opcode::Halt),
-            _ => Err(()),
-        }
-    }
-}
-
-pub struct Interpreter {
-    pub program: Vec<isize>,
-}
-
-impl Interpreter {
-    pub fn new(program: Vec<isize>) -> Interpreter {
-        Interpreter { program }
-    }
-
-    pub fn run(&self) -> Option<isize> {
-        let mut memory = self.program.clone();
-
-        let mut cursor = 0;
-        loop {
-            let mut instr = memory[cursor];
-            let opcode = instr % 100;
-            instr /= 100;
-            let opcode = Opcode::try_from(opcode as usize).ok()?;
-            match opcode {
-                Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::EqualTo => {
-                    let arg1 = memory[cursor + 1];
-                    let arg2 = memory[cursor + 2];
-                    let arg3 = memory[cursor + 3];
-
-                    let mode1 = instr % 10;
-                    instr /= 10;
-                    let mode2 = instr % 10;
-                    instr /= 10;
-                    let mode3 = instr % 10;
-
-                    let val1 = match mode1 {
-                        0 => memory[arg1 as usize],
-                        1 => arg1,
-                        _ => panic!("invalid parameter mode"),
-                    };
-                    let val2 = match mode2 {
-                        0 => memory[arg2 as usize],
-                        1 => arg2,
-                        _ => panic!("invalid parameter mode"),
-                    };
-
-                    if mode3 != 0 {
-                        panic!("invalid parameter mode");
-                    }
-
-                    memory[arg3 as usize] = match opcode {
-                        Opcode::Add => val1 + val2,
-                        Opcode::Multiply => val1 * val2,
-                        Opcode::LessThan => {
-                            if val1 < val2 {
-                                1
-                            } else {
-                                0
-                            }
-                        }
-                        Opcode::EqualTo => {
-                            if val1 == val2 {
-                                1
-                            } else {
-                                0
-                            }
-                        }
-                        _ => return None,
-                    };
-
-                    cursor += 4;
-                }
-                Opcode::Input => {
-                    let arg1 = memory[cursor + 1];
+use aoc_2019::intcode::{self, Encoding, Terminal, Vm};
+use aoc_2019::Error;
 
-                    if instr % 10 != 0 {
-                        return None;
-                    }
-
-                    let mut line = String::new();
-                    io::stdin().read_line(&mut line).ok()?;
-                    let value = line.trim().parse::<isize>().ok()?;
-
-                    memory[arg1 as usize] = value;
-
-                    cursor += 2;
-                }
-                Opcode::Output => {
-                    let arg1 = memory[cursor + 1];
-
-                    let val1 = match instr % 10 {
-                        0 => memory[arg1 as usize],
-                        1 => arg1,
-                        _ => panic!("invalid parameter mode"),
-                    };
-
-                    println!("{0}", val1);
-
-                    cursor += 2;
-                }
-                Opcode::JumpIfTrue => {
-                    let arg1 = memory[cursor + 1];
-                    let arg2 = memory[cursor + 2];
-
-                    let mode1 = instr % 10;
-                    instr /= 10;
-                    let mode2 = instr % 10;
-
-                    let val1 = match mode1 {
-                        0 => memory[arg1 as usize],
-                        1 => arg1,
-                        _ => panic!("invalid parameter mode"),
-                    };
-                    let val2 = match mode2 {
-                        0 => memory[arg2 as usize],
-                        1 => arg2,
-                        _ => panic!("invalid parameter mode"),
-                    };
-
-                    if val1 != 0 {
-                        cursor = val2 as usize;
-                    } else {
-                        cursor += 3;
-                    }
-                }
-                Opcode::JumpIfFalse => {
-                    let arg1 = memory[cursor + 1];
-                    let arg2 = memory[cursor + 2];
-
-                    let mode1 = instr % 10;
-                    instr /= 10;
-                    let mode2 = instr % 10;
-
-                    let val1 = match mode1 {
-                        0 => memory[arg1 as usize],
-                        1 => arg1,
-                        _ => panic!("invalid parameter mode"),
-                    };
-                    let val2 = match mode2 {
-                        0 => memory[arg2 as usize],
-                        1 => arg2,
-                        _ => panic!("invalid parameter mode"),
-                    };
-
-                    if val1 == 0 {
-                        cursor = val2 as usize;
-                    } else {
-                        cursor += 3;
-                    }
-                }
-                Opcode::Halt => break,
-            }
-        }
-
-        Some(memory[0])
-    }
-}
+pub static INPUT: &str = include_str!("../../inputs/day5.txt");
 
-fn main() {
-    let program = INPUT
-        .trim()
-        .split(',')
-        .flat_map(|chunk| chunk.parse().ok())
-        .collect();
-    let interpreter = Interpreter::new(program);
+/// Runs the diagnostic program interactively: it asks for the ID of the system to test on stdin, and prints its results.
+fn main() -> Result<(), Error> {
+    let program = intcode::parse(INPUT);
+    let mut terminal = Terminal::new(Vm::new(program), Encoding::Numeric);
 
-    interpreter.run().expect("invalid input program");
+    let stdin = io::stdin();
+    terminal.interact(stdin.lock(), io::stdout())
 }
@@ -58,8 +58,8 @@ fn main() {
 
     let part1: usize = orbit_map
         .inner()
-        .iter()
-        .map(|(k, _)| orbit_map.count_orbits(k))
+        .keys()
+        .map(|k| orbit_map.count_orbits(k))
         .sum();
     println!("{0}", part1);
 
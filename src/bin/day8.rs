@@ -43,7 +43,7 @@ fn main() {
         .expect("no layers at all");
     println!("{0}", part1);
 
-    let _part2 = input
+    input
         .chunks_exact(len)
         .rev()
         .fold(vec![Color::Transparent; len], |mut acc, layer| {
@@ -0,0 +1,24 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Read;
+
+use aoc_2019::intcode;
+use aoc_2019::Error;
+
+/// Assembles the Intcode source found in the file given as argument (or from stdin, if none is given).
+fn main() -> Result<(), Error> {
+    let source = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            source
+        }
+    };
+
+    let program = intcode::assemble(&source)?;
+    println!("{}", intcode::serialize(&program));
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Read;
+
+use aoc_2019::intcode::{self, Cfg};
+use aoc_2019::Error;
+
+/// Prints the control-flow graph of the Intcode program found in the file given as argument
+/// (or from stdin, if none is given), in the DOT language of Graphviz.
+fn main() -> Result<(), Error> {
+    let input = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+
+    let program = intcode::parse(&input);
+    let cfg = Cfg::new(&program);
+    print!("{}", cfg.to_dot());
+    for address in cfg.indirect_jumps() {
+        eprintln!("indirect jump at {:04}", address);
+    }
+
+    Ok(())
+}
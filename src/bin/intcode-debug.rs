@@ -0,0 +1,248 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+
+use aoc_2019::intcode::{self, Debugger, Stop, Vm};
+use aoc_2019::Error;
+
+static HELP: &str = "\
+commands:
+    s, step [n]         execute the next n instructions (defaults to 1)
+    c, continue         execute until a breakpoint or a watchpoint is hit, or until the program needs input or halts
+    rs, rstep [n]       revert the last n executed instructions (defaults to 1)
+    rc, rcontinue       revert executed instructions until a breakpoint or a watchpoint is hit
+    who <addr>          show the last instruction that wrote to a memory cell
+    b, break <addr>     set a breakpoint
+    db, delete <addr>   remove a breakpoint
+    w, watch <addr>     watch a memory cell for changes
+    dw, unwatch <addr>  stop watching a memory cell
+    i, input <values>   queue input values for the program
+    r, regs             show the registers
+    x <addr> [n]        dump n memory cells (defaults to 8)
+    set <addr> <value>  overwrite a memory cell, forgetting the history
+    l, list [addr] [n]  disassemble n instructions (defaults to 8) from an address (defaults to the instruction pointer)
+    info                list breakpoints and watchpoints
+    h, help             show this help
+    q, quit             exit the debugger";
+
+fn parse_address(arg: Option<&str>) -> Result<usize, String> {
+    let arg = arg.ok_or("missing address")?;
+    arg.parse()
+        .map_err(|_| format!("invalid address `{}`", arg))
+}
+
+fn parse_count(arg: Option<&str>, default: usize) -> Result<usize, String> {
+    match arg {
+        Some(arg) => arg.parse().map_err(|_| format!("invalid count `{}`", arg)),
+        None => Ok(default),
+    }
+}
+
+fn show_stop(dbg: &Debugger, stop: Stop) {
+    match stop {
+        Stop::Stepped => {}
+        Stop::Breakpoint(address) => println!("breakpoint hit at {}", address),
+        Stop::Watchpoint { address, old, new } => {
+            println!(
+                "watchpoint hit: [{}] changed from {} to {}",
+                address, old, new
+            )
+        }
+        Stop::Output(value) => println!("output: {}", value),
+        Stop::NeedsInput => println!("program needs input (use `input <values>`)"),
+        Stop::Halted => println!("program halted"),
+        Stop::HistoryStart => println!("reached the start of the history"),
+    }
+    show_current(dbg);
+}
+
+fn show_current(dbg: &Debugger) {
+    let ip = dbg.vm().ip();
+    match dbg.instruction_at(ip) {
+        Some(instruction) => println!("=> {:04}: {}", ip, instruction),
+        None => println!("=> {:04}: <invalid instruction {}>", ip, dbg.vm().read(ip)),
+    }
+}
+
+fn execute(dbg: &mut Debugger, line: &str) -> Result<bool, String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Ok(true),
+    };
+    match command {
+        "s" | "step" => {
+            let count = parse_count(words.next(), 1)?;
+            let mut stop = Stop::Stepped;
+            for _ in 0..count {
+                stop = dbg.step().map_err(|err| err.to_string())?;
+                if let Stop::Output(value) = stop {
+                    println!("output: {}", value);
+                    stop = Stop::Stepped;
+                }
+                if stop != Stop::Stepped {
+                    break;
+                }
+            }
+            show_stop(dbg, stop);
+        }
+        "c" | "continue" => loop {
+            match dbg.cont().map_err(|err| err.to_string())? {
+                Stop::Output(value) => println!("output: {}", value),
+                stop => {
+                    show_stop(dbg, stop);
+                    break;
+                }
+            }
+        },
+        "rs" | "rstep" => {
+            let count = parse_count(words.next(), 1)?;
+            let mut stop = Stop::Stepped;
+            for _ in 0..count {
+                stop = dbg.reverse_step();
+                if stop != Stop::Stepped {
+                    break;
+                }
+            }
+            show_stop(dbg, stop);
+        }
+        "rc" | "rcontinue" => {
+            let stop = dbg.reverse_cont();
+            show_stop(dbg, stop);
+        }
+        "who" => {
+            let address = parse_address(words.next())?;
+            match dbg.last_write(address) {
+                Some(event) => {
+                    let (_, value) = event.written.unwrap_or_default();
+                    let instruction = dbg
+                        .instruction_at(event.address)
+                        .map_or_else(|| event.opcode.mnemonic().to_string(), |it| it.to_string());
+                    println!(
+                        "[{}] was last set from {} to {} by {:04}: {}",
+                        address, event.previous, value, event.address, instruction
+                    );
+                }
+                None => println!("[{}] wasn't written to within the history", address),
+            }
+        }
+        "b" | "break" => {
+            let address = parse_address(words.next())?;
+            if dbg.set_breakpoint(address) {
+                println!("breakpoint set at {}", address);
+            }
+        }
+        "db" | "delete" => {
+            let address = parse_address(words.next())?;
+            if !dbg.remove_breakpoint(address) {
+                return Err(format!("no breakpoint at {}", address));
+            }
+        }
+        "w" | "watch" => {
+            let address = parse_address(words.next())?;
+            if dbg.set_watchpoint(address) {
+                println!("watching [{}] = {}", address, dbg.vm().read(address));
+            }
+        }
+        "dw" | "unwatch" => {
+            let address = parse_address(words.next())?;
+            if !dbg.remove_watchpoint(address) {
+                return Err(format!("no watchpoint on {}", address));
+            }
+        }
+        "i" | "input" => {
+            for word in words {
+                let value = word
+                    .parse()
+                    .map_err(|_| format!("invalid value `{}`", word))?;
+                dbg.vm_mut().push_input(value);
+            }
+        }
+        "r" | "regs" => {
+            let vm = dbg.vm();
+            println!("ip = {}", vm.ip());
+            println!("rb = {}", vm.relative_base());
+        }
+        "x" => {
+            let address = parse_address(words.next())?;
+            let count = parse_count(words.next(), 8)?;
+            for address in address..address.saturating_add(count) {
+                println!("[{:04}] = {}", address, dbg.vm().read(address));
+            }
+        }
+        "set" => {
+            let address = parse_address(words.next())?;
+            let value = words.next().ok_or("missing value")?;
+            let value = value
+                .parse()
+                .map_err(|_| format!("invalid value `{}`", value))?;
+            let forgotten = dbg.history().len();
+            dbg.write(address, value).map_err(|err| err.to_string())?;
+            if forgotten > 0 {
+                println!("history cleared ({} instructions)", forgotten);
+            }
+        }
+        "l" | "list" => {
+            let mut address = match words.next() {
+                Some(arg) => parse_address(Some(arg))?,
+                None => dbg.vm().ip(),
+            };
+            let count = parse_count(words.next(), 8)?;
+            for _ in 0..count {
+                let marker = if address == dbg.vm().ip() { "=>" } else { "  " };
+                match dbg.instruction_at(address) {
+                    Some(instruction) => {
+                        println!("{} {:04}: {}", marker, address, instruction);
+                        address = address.saturating_add(instruction.size());
+                    }
+                    None => {
+                        println!("{} {:04}: db {}", marker, address, dbg.vm().read(address));
+                        address = address.saturating_add(1);
+                    }
+                }
+            }
+        }
+        "info" => {
+            let breakpoints: Vec<String> =
+                dbg.breakpoints().iter().map(|it| it.to_string()).collect();
+            println!("breakpoints: {}", breakpoints.join(", "));
+            let watchpoints: Vec<String> = dbg
+                .watchpoints()
+                .iter()
+                .map(|(address, value)| format!("[{}] = {}", address, value))
+                .collect();
+            println!("watchpoints: {}", watchpoints.join(", "));
+        }
+        "h" | "help" => println!("{}", HELP),
+        "q" | "quit" => return Ok(false),
+        _ => return Err(format!("unknown command `{}` (try `help`)", command)),
+    }
+    Ok(true)
+}
+
+/// Debugs the Intcode program found in the file given as argument.
+fn main() -> Result<(), Error> {
+    let path = env::args().nth(1).ok_or("usage: intcode-debug <program>")?;
+    let program = intcode::parse(&fs::read_to_string(path)?);
+    let mut dbg = Debugger::new(Vm::new(program));
+
+    show_current(&dbg);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(icdb) ");
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match execute(&mut dbg, &line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => println!("error: {}", err),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::env;
+use std::fs;
+use std::io;
+
+use aoc_2019::intcode::{self, Profiler, State, TraceWriter, Vm};
+use aoc_2019::Error;
+
+/// Runs the Intcode program found in the file given as argument with the given input values,
+/// then reports where it spent its time.
+/// With `--trace <file>`, every executed instruction also gets recorded to that file.
+fn main() -> Result<(), Error> {
+    let mut args = env::args().skip(1);
+    let mut path = None;
+    let mut trace = None;
+    let mut inputs = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" => trace = Some(args.next().ok_or("missing trace file")?),
+            _ if path.is_none() => path = Some(arg),
+            _ => inputs.push(arg.parse::<isize>()?),
+        }
+    }
+    let path = path.ok_or("usage: intcode-profile <program> [inputs...] [--trace <file>]")?;
+
+    let program = intcode::parse(&fs::read_to_string(path)?);
+    let mut vm = Vm::new(program);
+    for input in inputs {
+        vm.push_input(input);
+    }
+
+    let mut profiler = Profiler::new();
+    let mut writer = match trace {
+        Some(trace) => Some(TraceWriter::new(io::BufWriter::new(fs::File::create(
+            trace,
+        )?))?),
+        None => None,
+    };
+    loop {
+        let state = match writer.as_mut() {
+            Some(writer) => vm.run_until_io_with(&mut (&mut profiler, writer))?,
+            None => vm.run_until_io_with(&mut profiler)?,
+        };
+        match state {
+            State::Output(value) => println!("{}", value),
+            State::NeedsInput => {
+                eprintln!("program ran out of inputs");
+                break;
+            }
+            State::Halted | State::Running => break,
+        }
+    }
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+
+    println!();
+    print!("{}", profiler);
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Read;
+
+use aoc_2019::intcode;
+use aoc_2019::Error;
+
+/// Transpiles the Intcode program found in the file given as argument (or from stdin, if none is given) into Rust.
+fn main() -> Result<(), Error> {
+    let input = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+
+    let program = intcode::parse(&input);
+    print!("{}", intcode::transpile(&program));
+
+    Ok(())
+}
@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use super::{Instruction, Mode, Opcode, Operand};
+
+/// The maximum nesting depth of macro expansions and constant definitions.
+const MAX_DEPTH: usize = 64;
+
+/// An error encountered while assembling a program.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AsmError {
+    /// The line (starting at 1) the error was found at.
+    pub line: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for AsmError {}
+
+/// A value known only once every label has been placed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Number(isize),
+    Symbol(String),
+}
+
+/// A sum of terms, such as `buffer+2` or `END-START`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expr(Vec<(isize, Term)>);
+
+/// A line of source, after macro expansion.
+#[derive(Debug, Clone)]
+struct SourceLine {
+    line: usize,
+    text: String,
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Instruction(Opcode, Vec<(Mode, Expr)>),
+    Data(Vec<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Symbol {
+    Label(usize),
+    Constant(Expr, usize),
+}
+
+#[derive(Debug, Clone)]
+struct Macro {
+    params: Vec<String>,
+    body: Vec<SourceLine>,
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Removes the comment from a line, leaving semicolons within string literals alone.
+fn strip_comment(text: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &text[..idx],
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Splits a list of comma-separated arguments, leaving commas within string literals alone.
+fn split_args(text: &str) -> Vec<&str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let mut args = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                args.push(text[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(text[start..].trim());
+    args
+}
+
+/// Splits a statement into its first word and the rest of it.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Some(idx) => (&text[..idx], text[idx..].trim()),
+        None => (text, ""),
+    }
+}
+
+fn parse_expr(line: usize, text: &str) -> Result<Expr, AsmError> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if text.is_empty() {
+        return Err(AsmError::new(line, "missing value"));
+    }
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut start = 0;
+    let bytes = text.as_bytes();
+    for idx in 0..=bytes.len() {
+        let at_end = idx == bytes.len();
+        if !at_end && (bytes[idx] != b'+' && bytes[idx] != b'-' || idx == start) {
+            continue;
+        }
+        let token = &text[start..idx];
+        let term = if let Ok(number) = token.parse::<isize>() {
+            Term::Number(number)
+        } else if is_identifier(token) {
+            Term::Symbol(token.to_string())
+        } else if token.starts_with('-') && is_identifier(&token[1..]) {
+            sign = -sign;
+            Term::Symbol(token[1..].to_string())
+        } else {
+            return Err(AsmError::new(line, format!("invalid value `{}`", text)));
+        };
+        terms.push((sign, term));
+        if !at_end {
+            sign = if bytes[idx] == b'+' { 1 } else { -1 };
+            start = idx + 1;
+        }
+    }
+    Ok(Expr(terms))
+}
+
+fn parse_operand(line: usize, text: &str) -> Result<(Mode, Expr), AsmError> {
+    let text = text.trim();
+    if text.starts_with('[') && text.ends_with(']') {
+        Ok((Mode::Position, parse_expr(line, &text[1..text.len() - 1])?))
+    } else if let Some(value) = text.strip_prefix('#') {
+        Ok((Mode::Immediate, parse_expr(line, value)?))
+    } else if text == "rb" {
+        Ok((Mode::Relative, Expr(vec![(1, Term::Number(0))])))
+    } else if let Some(offset) = text.strip_prefix("rb").map(str::trim_start) {
+        match offset.strip_prefix('+') {
+            Some(offset) => Ok((Mode::Relative, parse_expr(line, offset)?)),
+            None if offset.starts_with('-') => Ok((Mode::Relative, parse_expr(line, offset)?)),
+            None => Err(AsmError::new(
+                line,
+                format!("invalid relative operand `{}`", text),
+            )),
+        }
+    } else {
+        Err(AsmError::new(
+            line,
+            format!(
+                "operand `{}` has no mode, expected `[x]`, `#x` or `rb+x`",
+                text
+            ),
+        ))
+    }
+}
+
+fn parse_string(line: usize, text: &str) -> Result<Vec<isize>, AsmError> {
+    let mut values = Vec::new();
+    let mut chars = text[1..text.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some(c @ '\\') | Some(c @ '"') => c,
+                _ => return Err(AsmError::new(line, "invalid escape sequence")),
+            },
+            c => c,
+        };
+        values.push(c as isize);
+    }
+    Ok(values)
+}
+
+/// Expands macro definitions and invocations, returning the resulting lines.
+fn expand(
+    lines: Vec<SourceLine>,
+    macros: &mut HashMap<String, Macro>,
+    counter: &mut usize,
+    depth: usize,
+) -> Result<Vec<SourceLine>, AsmError> {
+    let mut output = Vec::new();
+    let mut lines = lines.into_iter();
+    while let Some(source) = lines.next() {
+        let (word, rest) = split_word(&source.text);
+        if word == "macro" {
+            let (name, params) = split_word(rest);
+            if !is_identifier(name) {
+                return Err(AsmError::new(source.line, "invalid macro name"));
+            }
+            let params: Vec<String> = split_args(params).into_iter().map(String::from).collect();
+            let mut body = Vec::new();
+            loop {
+                match lines.next() {
+                    Some(line) if line.text.trim() == "endm" => break,
+                    Some(line) => body.push(line),
+                    None => return Err(AsmError::new(source.line, "unterminated macro")),
+                }
+            }
+            macros.insert(name.to_string(), Macro { params, body });
+        } else if let Some(mac) = macros.get(word).cloned() {
+            if depth >= MAX_DEPTH {
+                return Err(AsmError::new(source.line, "macro expansion too deep"));
+            }
+            let args = split_args(rest);
+            if args.len() != mac.params.len() {
+                return Err(AsmError::new(
+                    source.line,
+                    format!(
+                        "macro `{}` expects {} arguments, got {}",
+                        word,
+                        mac.params.len(),
+                        args.len()
+                    ),
+                ));
+            }
+            *counter += 1;
+            let body = mac
+                .body
+                .iter()
+                .map(|line| {
+                    let text = substitute(&line.text, &mac.params, &args);
+                    SourceLine {
+                        line: source.line,
+                        text: text.replace("\\@", &counter.to_string()),
+                    }
+                })
+                .collect();
+            output.extend(expand(body, macros, counter, depth + 1)?);
+        } else {
+            output.push(source);
+        }
+    }
+    Ok(output)
+}
+
+/// Replaces every whole-word occurrence of the parameters with their arguments.
+fn substitute(text: &str, params: &[String], args: &[&str]) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, output: &mut String| {
+        match params.iter().position(|param| param == word) {
+            Some(idx) => output.push_str(args[idx]),
+            None => output.push_str(word),
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut output);
+            output.push(c);
+        }
+    }
+    flush(&mut word, &mut output);
+    output
+}
+
+struct Assembler {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl Assembler {
+    fn define(&mut self, line: usize, name: &str, symbol: Symbol) -> Result<(), AsmError> {
+        if !is_identifier(name) || name == "rb" {
+            return Err(AsmError::new(
+                line,
+                format!("invalid symbol name `{}`", name),
+            ));
+        }
+        if self.symbols.insert(name.to_string(), symbol).is_some() {
+            return Err(AsmError::new(
+                line,
+                format!("symbol `{}` defined twice", name),
+            ));
+        }
+        Ok(())
+    }
+
+    fn eval(&self, line: usize, expr: &Expr, depth: usize) -> Result<isize, AsmError> {
+        if depth >= MAX_DEPTH {
+            return Err(AsmError::new(line, "constant definitions are too deep"));
+        }
+        expr.0.iter().try_fold(0, |acc, (sign, term)| {
+            let value = match term {
+                Term::Number(number) => *number,
+                Term::Symbol(name) => match self.symbols.get(name) {
+                    Some(Symbol::Label(address)) => *address as isize,
+                    Some(Symbol::Constant(expr, line)) => self.eval(*line, expr, depth + 1)?,
+                    None => {
+                        return Err(AsmError::new(line, format!("undefined symbol `{}`", name)))
+                    }
+                },
+            };
+            sign.checked_mul(value)
+                .and_then(|value| value.checked_add(acc))
+                .ok_or_else(|| AsmError::new(line, "value out of range"))
+        })
+    }
+}
+
+/// Assembles a program from its textual form.
+///
+/// Each line holds optional labels (`name:`), then an instruction, a directive or a macro invocation,
+/// with comments starting with `;`:
+///
+/// - instructions are written as `add`, `mul`, `in`, `out`, `jt`, `jf`, `lt`, `eq`, `arb` or `hlt`,
+///   with operands in position (`[x]`), immediate (`#x`) or relative (`rb+x`, spaces allowed) mode.
+/// - `db a, b, "text"` places raw values (strings become their character codes).
+/// - `const NAME = x` defines a constant.
+/// - `macro NAME a, b` ... `endm` defines a macro, `\@` in its body expands to a number unique to each invocation.
+///
+/// Values can be numbers, labels, constants or sums of these (`buffer+2`).
+pub fn assemble(source: &str) -> Result<Vec<isize>, AsmError> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(idx, text)| SourceLine {
+            line: idx + 1,
+            text: strip_comment(text).trim().to_string(),
+        })
+        .filter(|source| !source.text.is_empty())
+        .collect();
+    let lines = expand(lines, &mut HashMap::new(), &mut 0, 0)?;
+
+    // first pass: place labels and parse statements.
+    let mut asm = Assembler {
+        symbols: HashMap::new(),
+    };
+    let mut statements = Vec::new();
+    let mut address = 0;
+    for source in lines {
+        let line = source.line;
+        let mut text = source.text.as_str();
+        while let Some(idx) = text.find(':') {
+            let name = text[..idx].trim();
+            if !is_identifier(name) {
+                break;
+            }
+            asm.define(line, name, Symbol::Label(address))?;
+            text = text[idx + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (word, rest) = split_word(text);
+        let statement = match word {
+            "const" => {
+                let mut parts = rest.splitn(2, '=');
+                let name = parts.next().unwrap_or("").trim();
+                let value = parts
+                    .next()
+                    .ok_or_else(|| AsmError::new(line, "expected `const NAME = value`"))?;
+                let expr = parse_expr(line, value)?;
+                asm.define(line, name, Symbol::Constant(expr, line))?;
+                continue;
+            }
+            "db" => {
+                let mut values = Vec::new();
+                for arg in split_args(rest) {
+                    if arg.len() >= 2 && arg.starts_with('"') && arg.ends_with('"') {
+                        let chars = parse_string(line, arg)?;
+                        values.extend(chars.into_iter().map(|c| Expr(vec![(1, Term::Number(c))])));
+                    } else {
+                        values.push(parse_expr(line, arg)?);
+                    }
+                }
+                Statement::Data(values)
+            }
+            mnemonic => {
+                let opcode = Opcode::from_mnemonic(mnemonic).ok_or_else(|| {
+                    AsmError::new(line, format!("unknown instruction `{}`", mnemonic))
+                })?;
+                let operands = split_args(rest)
+                    .into_iter()
+                    .map(|arg| parse_operand(line, arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if operands.len() != opcode.arity() {
+                    return Err(AsmError::new(
+                        line,
+                        format!(
+                            "`{}` expects {} operands, got {}",
+                            mnemonic,
+                            opcode.arity(),
+                            operands.len()
+                        ),
+                    ));
+                }
+                let writes_immediate = opcode.writes()
+                    && operands.last().map(|(mode, _)| *mode) == Some(Mode::Immediate);
+                if writes_immediate {
+                    return Err(AsmError::new(line, "destination in immediate mode"));
+                }
+                Statement::Instruction(opcode, operands)
+            }
+        };
+        address += match &statement {
+            Statement::Instruction(opcode, _) => 1 + opcode.arity(),
+            Statement::Data(values) => values.len(),
+        };
+        statements.push((line, statement));
+    }
+
+    // second pass: resolve values and encode.
+    let mut program = Vec::with_capacity(address);
+    for (line, statement) in statements {
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let operands = operands
+                    .iter()
+                    .map(|(mode, expr)| {
+                        let value = asm.eval(line, expr, 0)?;
+                        Ok(Operand { mode: *mode, value })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                program.extend(Instruction { opcode, operands }.encode());
+            }
+            Statement::Data(values) => {
+                for expr in values {
+                    program.push(asm.eval(line, &expr, 0)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
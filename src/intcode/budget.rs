@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+use super::Error;
+
+/// The number of instructions executed between two checks of the clock.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Limits on the resources a machine can use, to safely run programs that may never halt.
+/// Every limit is unset by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Budget {
+    /// The maximum number of instructions to execute.
+    pub fuel: Option<u64>,
+    /// The maximum wall-clock time to run for, waiting for inputs included.
+    pub timeout: Option<Duration>,
+    /// The maximum number of memory cells, on top of the limit of the memory backend.
+    pub memory: Option<usize>,
+}
+
+/// The fuel and time budget being spent by a machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The instruction count at which the machine runs out of fuel.
+    fuel_end: Option<u64>,
+    deadline: Option<Instant>,
+}
+
+impl Limits {
+    /// Starts spending a budget, for a machine that already executed `steps` instructions.
+    pub fn new(budget: Budget, steps: u64) -> Limits {
+        Limits {
+            fuel_end: budget.fuel.map(|fuel| steps.saturating_add(fuel)),
+            deadline: budget.timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+
+    /// Checks whether the instruction at `address` can be executed, as the instruction number `steps`.
+    /// Returns the instruction number at which the budget needs checking again, the clock being only read every few instructions.
+    #[cold]
+    pub fn check(&self, steps: u64, address: usize) -> Result<u64, Error> {
+        if matches!(self.fuel_end, Some(end) if steps >= end) {
+            return Err(Error::OutOfFuel { address });
+        }
+        if self.remaining_time() == Some(Duration::from_secs(0)) {
+            return Err(Error::TimedOut { address });
+        }
+        let clock = self.deadline.map(|_| steps + CLOCK_INTERVAL);
+        let next = clock.into_iter().chain(self.fuel_end).min();
+        Ok(next.unwrap_or(u64::MAX))
+    }
+
+    /// Returns the time left before the deadline, if there is one.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        let deadline = self.deadline?;
+        Some(deadline.saturating_duration_since(Instant::now()))
+    }
+}
@@ -0,0 +1,179 @@
+//! A packet bus for machines that read -1 whenever no packet is waiting for them.
+//!
+//! Machines run synchronously, in turn on the calling thread, rather than as `Vm::run_async` tasks over channels
+//! with `InputPolicy::Default(-1)`: the order in which async tasks get scheduled isn't reproducible,
+//! and the bus is only idle if every machine found its queue empty during the same round, which needs fixed turns.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::mem;
+
+use super::{Dense, Memory, NetworkError, State, Vm};
+
+/// The address of the monitor, unless configured otherwise.
+pub const MONITOR_ADDRESS: isize = 255;
+
+/// A message exchanged by the machines of a bus, sent as three output values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Packet {
+    pub address: isize,
+    pub x: isize,
+    pub y: isize,
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}) to {}", self.x, self.y, self.address)
+    }
+}
+
+/// What a bus stopped running for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Traffic {
+    /// A packet was sent to the monitor.
+    Monitor(Packet),
+    /// A packet was sent to an address that is neither a machine nor the monitor, and got dropped.
+    Undelivered(Packet),
+    /// No machine had any packet to read, and none sent any.
+    Idle,
+    /// Every machine halted.
+    Halted,
+}
+
+/// Machines exchanging packets on a shared bus, each one being known by its position on the bus.
+///
+/// Every machine reads its address first, then the `x` and `y` values of the packets sent to it,
+/// or -1 when there is none: reading never blocks.
+/// Machines are run in turn, each one until it waits for an input again, so that runs are deterministic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bus<M: Memory<Word = isize> = Dense> {
+    nodes: Vec<Vm<M>>,
+    halted: Vec<bool>,
+    /// The values of the packet each machine is in the middle of sending.
+    partial: Vec<Vec<isize>>,
+    monitor: isize,
+    /// The machine whose turn is next.
+    next: usize,
+    /// Whether no packet was read or sent yet during the current round.
+    idle: bool,
+}
+
+impl Bus {
+    /// Boots `size` machines running the same program.
+    pub fn boot(program: &[isize], size: usize) -> Bus {
+        Bus::new((0..size).map(|_| Vm::new(program.to_vec())))
+    }
+}
+
+impl<M: Memory<Word = isize>> Bus<M> {
+    /// Puts the machines on a bus, giving them their address.
+    pub fn new(vms: impl IntoIterator<Item = Vm<M>>) -> Bus<M> {
+        let nodes: Vec<_> = vms
+            .into_iter()
+            .enumerate()
+            .map(|(address, mut vm)| {
+                vm.push_input(address as isize);
+                vm
+            })
+            .collect();
+        Bus {
+            halted: vec![false; nodes.len()],
+            partial: vec![Vec::new(); nodes.len()],
+            nodes,
+            monitor: MONITOR_ADDRESS,
+            next: 0,
+            idle: true,
+        }
+    }
+
+    /// Changes the address packets must be sent to for the monitor to observe them.
+    pub fn set_monitor(&mut self, address: isize) {
+        self.monitor = address;
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, address: usize) -> &Vm<M> {
+        &self.nodes[address]
+    }
+
+    /// Queues a packet for the machine it is addressed to, such as one sent back by the monitor.
+    /// Gives the packet back if it isn't addressed to a machine of the bus.
+    pub fn send(&mut self, packet: Packet) -> Result<(), Packet> {
+        let vm = usize::try_from(packet.address)
+            .ok()
+            .and_then(|address| self.nodes.get_mut(address))
+            .ok_or(packet)?;
+        vm.push_input(packet.x);
+        vm.push_input(packet.y);
+        Ok(())
+    }
+
+    /// Runs the machines until a packet is sent to the monitor or out of the bus, or until the bus is idle or halted.
+    /// The next call resumes with the packets and the machines left, without starting a new round.
+    pub fn poll(&mut self) -> Result<Traffic, NetworkError> {
+        loop {
+            if let Some(node) = self.next.checked_sub(1) {
+                if let Some(traffic) = self.route(node) {
+                    return Ok(traffic);
+                }
+            }
+            if self.halted.iter().all(|&halted| halted) {
+                return Ok(Traffic::Halted);
+            }
+            if self.next == self.nodes.len() {
+                self.next = 0;
+                if mem::replace(&mut self.idle, true) {
+                    return Ok(Traffic::Idle);
+                }
+            }
+
+            let node = self.next;
+            self.next += 1;
+            if self.halted[node] {
+                continue;
+            }
+            let vm = &mut self.nodes[node];
+            if vm.pending_inputs().is_empty() {
+                vm.push_input(-1);
+            } else {
+                self.idle = false;
+            }
+
+            let state = vm
+                .run()
+                .map_err(|error| NetworkError::Fault { node, error })?;
+            self.halted[node] = state == State::Halted;
+
+            let outputs = vm.take_outputs();
+            self.idle &= outputs.is_empty();
+            self.partial[node].extend(outputs);
+        }
+    }
+
+    /// Delivers the packets a machine finished sending,
+    /// stopping at the first one the monitor or the caller must know about.
+    fn route(&mut self, node: usize) -> Option<Traffic> {
+        while self.partial[node].len() >= 3 {
+            let values: Vec<_> = self.partial[node].drain(..3).collect();
+            let packet = Packet {
+                address: values[0],
+                x: values[1],
+                y: values[2],
+            };
+            if packet.address == self.monitor {
+                return Some(Traffic::Monitor(packet));
+            }
+            if let Err(packet) = self.send(packet) {
+                return Some(Traffic::Undelivered(packet));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use super::{disassemble, label, Instruction, Item, Opcode};
+
+/// A run of instructions only entered from its first one, and only left from its last one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BasicBlock {
+    /// The address of the first instruction.
+    pub start: usize,
+    /// The address right after the last instruction.
+    pub end: usize,
+    /// The instructions of the block, along with their addresses.
+    pub instructions: Vec<(usize, Instruction)>,
+}
+
+impl BasicBlock {
+    /// Returns the last instruction of the block, along with its address.
+    pub fn last(&self) -> &(usize, Instruction) {
+        self.instructions
+            .last()
+            .expect("blocks have at least one instruction")
+    }
+
+    /// Returns whether the block ends with a jump whose target is computed at runtime.
+    pub fn is_indirect(&self) -> bool {
+        let (_, instruction) = self.last();
+        instruction.jump_operand().is_some() && instruction.jump_target().is_none()
+    }
+
+    /// Returns whether the block ends with a halt instruction.
+    pub fn halts(&self) -> bool {
+        self.last().1.opcode == Opcode::Halt
+    }
+}
+
+/// How control flows along an edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// A jump to an immediate target is taken.
+    Jump,
+    /// Execution continues with the next instruction in memory.
+    Fallthrough,
+}
+
+/// An edge between the blocks starting at `from` and `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// The control-flow graph of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    /// The blocks of reachable code, ordered by address.
+    pub blocks: Vec<BasicBlock>,
+    /// The edges between blocks, jumps with computed targets don't have any.
+    pub edges: Vec<Edge>,
+}
+
+impl Cfg {
+    /// Builds the control-flow graph of a program, from the code found by the disassembler.
+    /// Blocks start at the entry point, at jump targets, at stored code pointers and after jumps.
+    pub fn new(program: &[isize]) -> Cfg {
+        let listing = disassemble(program);
+        let mut leaders: BTreeSet<usize> = listing.labels;
+        leaders.insert(0);
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut open = false;
+        for line in listing.lines {
+            let instruction = match line.item {
+                Item::Instruction(instruction) => instruction,
+                Item::Data(_) => {
+                    open = false;
+                    continue;
+                }
+            };
+            if !open || leaders.contains(&line.address) {
+                blocks.push(BasicBlock {
+                    start: line.address,
+                    end: line.address,
+                    instructions: Vec::new(),
+                });
+            }
+            let block = blocks.last_mut().expect("a block was just opened");
+            block.end = line.address + instruction.size();
+            open = instruction.jump_operand().is_none() && instruction.opcode != Opcode::Halt;
+            block.instructions.push((line.address, instruction));
+        }
+
+        let starts: BTreeSet<usize> = blocks.iter().map(|block| block.start).collect();
+        let mut edges = Vec::new();
+        for block in blocks.iter() {
+            let (_, instruction) = block.last();
+            let targets = instruction
+                .jump_target()
+                .map(|target| (target, EdgeKind::Jump))
+                .into_iter();
+            let next =
+                Some((block.end, EdgeKind::Fallthrough)).filter(|_| instruction.falls_through());
+            for (to, kind) in targets.chain(next) {
+                if starts.contains(&to) {
+                    edges.push(Edge {
+                        from: block.start,
+                        to,
+                        kind,
+                    });
+                }
+            }
+        }
+
+        Cfg { blocks, edges }
+    }
+
+    /// Returns the block containing the given address, if it's part of one.
+    pub fn block_containing(&self, address: usize) -> Option<&BasicBlock> {
+        let idx = match self
+            .blocks
+            .binary_search_by_key(&address, |block| block.start)
+        {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        Some(&self.blocks[idx]).filter(|block| address < block.end)
+    }
+
+    /// Returns the edges leaving the block starting at the given address.
+    pub fn successors(&self, start: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == start)
+    }
+
+    /// Returns the addresses of the jumps whose targets are computed at runtime.
+    pub fn indirect_jumps(&self) -> Vec<usize> {
+        let blocks = self.blocks.iter().filter(|block| block.is_indirect());
+        blocks.map(|block| block.last().0).collect()
+    }
+
+    /// Renders the graph in the DOT language of Graphviz.
+    ///
+    /// Taken jumps are drawn as solid edges and fall-throughs as dashed ones,
+    /// indirect jumps get a dotted edge to a node standing for any address.
+    /// The entry block is drawn in bold, and halting blocks with a double border.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for block in self.blocks.iter() {
+            let mut text = format!("{}:\\l", label(block.start));
+            for (address, instruction) in block.instructions.iter() {
+                write!(text, "{:04}: {}\\l", address, instruction).unwrap();
+            }
+            let mut attributes = format!("label=\"{}\"", text);
+            if block.start == 0 {
+                attributes.push_str(", style=bold");
+            }
+            if block.halts() {
+                attributes.push_str(", peripheries=2");
+            }
+            writeln!(dot, "    {} [{}];", label(block.start), attributes).unwrap();
+        }
+        for edge in self.edges.iter() {
+            let style = match edge.kind {
+                EdgeKind::Jump => "solid",
+                EdgeKind::Fallthrough => "dashed",
+            };
+            writeln!(
+                dot,
+                "    {} -> {} [style={}];",
+                label(edge.from),
+                label(edge.to),
+                style
+            )
+            .unwrap();
+        }
+        if self.blocks.iter().any(|block| block.is_indirect()) {
+            writeln!(dot, "    indirect [shape=ellipse, label=\"?\"];").unwrap();
+            for block in self.blocks.iter().filter(|block| block.is_indirect()) {
+                writeln!(
+                    dot,
+                    "    {} -> indirect [style=dotted];",
+                    label(block.start)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
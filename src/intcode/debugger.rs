@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Dense, Error, Event, History, Instruction, Memory, State, Vm};
+
+/// The number of executed instructions remembered by the debugger, for reverse execution.
+pub const HISTORY_CAPACITY: usize = 1 << 20;
+
+/// The reason the debugger gave control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stop {
+    /// A single instruction was executed.
+    Stepped,
+    /// The instruction pointer reached a breakpoint.
+    Breakpoint(usize),
+    /// A watched memory cell changed value.
+    Watchpoint {
+        address: usize,
+        old: isize,
+        new: isize,
+    },
+    /// The program produced a value.
+    Output(isize),
+    /// The program is waiting for an input value.
+    NeedsInput,
+    /// The program halted.
+    Halted,
+    /// There is no more history to step back into.
+    HistoryStart,
+}
+
+/// A machine under the control of a debugger.
+#[derive(Debug, Clone)]
+pub struct Debugger<M: Memory = Dense> {
+    vm: Vm<M>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, isize>,
+    history: History,
+}
+
+impl<M: Memory<Word = isize>> Debugger<M> {
+    pub fn new(vm: Vm<M>) -> Debugger<M> {
+        Debugger {
+            vm,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            history: History::with_capacity(HISTORY_CAPACITY),
+        }
+    }
+
+    pub fn vm(&self) -> &Vm<M> {
+        &self.vm
+    }
+
+    pub fn vm_mut(&mut self) -> &mut Vm<M> {
+        &mut self.vm
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+
+    /// Returns the watched addresses, along with their last known values.
+    pub fn watchpoints(&self) -> &BTreeMap<usize, isize> {
+        &self.watchpoints
+    }
+
+    /// Returns the executed instructions that can be stepped back into.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Returns the last remembered instruction that wrote to the given memory cell.
+    pub fn last_write(&self, address: usize) -> Option<&Event> {
+        self.history.last_write(address)
+    }
+
+    /// Sets a breakpoint, returns `false` if one was already set at this address.
+    pub fn set_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.insert(address)
+    }
+
+    /// Removes a breakpoint, returns `false` if none was set at this address.
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    /// Starts watching a memory cell, returns `false` if it was already watched.
+    pub fn set_watchpoint(&mut self, address: usize) -> bool {
+        let value = self.vm.read(address);
+        self.watchpoints.insert(address, value).is_none()
+    }
+
+    /// Stops watching a memory cell, returns `false` if it wasn't watched.
+    pub fn remove_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.remove(&address).is_some()
+    }
+
+    /// Overwrites a memory cell.
+    /// Edits can't be stepped back over, so the history is forgotten.
+    pub fn write(&mut self, address: usize, value: isize) -> Result<(), Error> {
+        self.vm.write(address, value)?;
+        self.history.clear();
+        if let Some(old) = self.watchpoints.get_mut(&address) {
+            *old = value;
+        }
+        Ok(())
+    }
+
+    /// Decodes the instruction at the given address.
+    pub fn instruction_at(&self, address: usize) -> Option<Instruction> {
+        let cells: Vec<isize> = (address..address.saturating_add(4))
+            .map(|address| self.vm.read(address))
+            .collect();
+        Instruction::decode(&cells, 0)
+    }
+
+    /// Returns the first watched cell whose value changed since last checked, updating all of them.
+    fn check_watchpoints(&mut self) -> Option<Stop> {
+        let mut stop = None;
+        for (&address, old) in self.watchpoints.iter_mut() {
+            let new = self.vm.read(address);
+            if *old != new {
+                stop = stop.or(Some(Stop::Watchpoint {
+                    address,
+                    old: *old,
+                    new,
+                }));
+                *old = new;
+            }
+        }
+        stop
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) -> Result<Stop, Error> {
+        let stop = match self.vm.step_with(&mut self.history)? {
+            State::Running => Stop::Stepped,
+            State::Output(value) => Stop::Output(value),
+            State::NeedsInput => return Ok(Stop::NeedsInput),
+            State::Halted => return Ok(Stop::Halted),
+        };
+        Ok(self.check_watchpoints().unwrap_or(stop))
+    }
+
+    /// Executes instructions until a breakpoint or a watchpoint is hit, or until the program does some I/O or halts.
+    /// A breakpoint set at the current instruction doesn't stop execution, so that continuing from it works.
+    pub fn cont(&mut self) -> Result<Stop, Error> {
+        loop {
+            match self.step()? {
+                Stop::Stepped => {}
+                stop => return Ok(stop),
+            }
+            if self.breakpoints.contains(&self.vm.ip()) {
+                return Ok(Stop::Breakpoint(self.vm.ip()));
+            }
+        }
+    }
+
+    /// Reverts the last executed instruction.
+    pub fn reverse_step(&mut self) -> Stop {
+        match self.history.undo(&mut self.vm) {
+            Some(_) => self.check_watchpoints().unwrap_or(Stop::Stepped),
+            None => Stop::HistoryStart,
+        }
+    }
+
+    /// Reverts executed instructions until a breakpoint or a watchpoint is hit, or until the start of the history.
+    pub fn reverse_cont(&mut self) -> Stop {
+        loop {
+            match self.reverse_step() {
+                Stop::Stepped => {}
+                stop => return stop,
+            }
+            if self.breakpoints.contains(&self.vm.ip()) {
+                return Stop::Breakpoint(self.vm.ip());
+            }
+        }
+    }
+}
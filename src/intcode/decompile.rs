@@ -0,0 +1,775 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+use super::{label, BasicBlock, Cfg, Instruction, Mode, Opcode, Operand};
+
+/// Stands for the end of a function, in post-dominator computations.
+const EXIT: usize = usize::MAX;
+
+/// How control leaves a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    /// Execution continues at the given address.
+    Goto(usize),
+    /// A conditional jump, to `taken` if its condition holds, to `next` otherwise.
+    Branch {
+        taken: usize,
+        next: usize,
+    },
+    /// A function gets called, which returns to the given address.
+    Call {
+        target: usize,
+        ret: usize,
+    },
+    /// The return address stored by the caller is jumped to.
+    Return,
+    Halt,
+    /// A jump to a computed address, or to something that isn't code.
+    /// Execution continues at `next` if the jump is conditional and not taken.
+    Indirect {
+        next: Option<usize>,
+    },
+    /// Execution runs into something that isn't code.
+    End,
+}
+
+/// The idioms recognized in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Shape {
+    exit: Exit,
+    /// The index of the instruction storing the return address of a call.
+    ret_store: Option<usize>,
+    /// The index of the instruction releasing the stack frame before a return.
+    epilogue: Option<usize>,
+}
+
+/// Whether a jump is always taken, never taken, or depends on its condition.
+fn certainty(instruction: &Instruction) -> Option<bool> {
+    let cond = instruction.operands[0];
+    if cond.mode != Mode::Immediate {
+        return None;
+    }
+    Some((cond.value != 0) == (instruction.opcode == Opcode::JumpIfTrue))
+}
+
+fn shape(block: &BasicBlock, cfg: &Cfg) -> Shape {
+    let (_, last) = block.last();
+    let mut shape = Shape {
+        exit: Exit::End,
+        ret_store: None,
+        epilogue: None,
+    };
+    let starts = |address: usize| cfg.block_containing(address).map(|it| it.start) == Some(address);
+    let goto = |address: usize| {
+        if starts(address) {
+            Exit::Goto(address)
+        } else {
+            Exit::End
+        }
+    };
+    shape.exit = match (last.opcode, last.jump_operand()) {
+        (Opcode::Halt, _) => Exit::Halt,
+        (_, None) => goto(block.end),
+        (_, Some(_)) => {
+            let target = last.operands[1];
+            match (certainty(last), last.jump_target()) {
+                (Some(false), _) => goto(block.end),
+                (Some(true), Some(target)) => {
+                    let len = block.instructions.len();
+                    let ret_store = block.instructions[..len - 1]
+                        .iter()
+                        .rposition(|(_, instruction)| stores_return(instruction, block.end));
+                    match ret_store {
+                        Some(idx) if starts(target) && starts(block.end) => {
+                            shape.ret_store = Some(idx);
+                            Exit::Call {
+                                target,
+                                ret: block.end,
+                            }
+                        }
+                        _ => goto(target),
+                    }
+                }
+                (Some(true), None) if target == relative(0) => {
+                    let len = block.instructions.len();
+                    if len > 1 && block.instructions[len - 2].1.opcode == Opcode::AdjustRelativeBase
+                    {
+                        shape.epilogue = Some(len - 2);
+                    }
+                    Exit::Return
+                }
+                (Some(true), _) => Exit::Indirect { next: None },
+                (None, Some(taken)) if starts(taken) && starts(block.end) => Exit::Branch {
+                    taken,
+                    next: block.end,
+                },
+                (None, _) => Exit::Indirect {
+                    next: Some(block.end).filter(|&next| starts(next)),
+                },
+            }
+        }
+    };
+    shape
+}
+
+fn relative(value: isize) -> Operand {
+    Operand {
+        mode: Mode::Relative,
+        value,
+    }
+}
+
+/// Returns whether an instruction stores the given return address into the slot under the relative base.
+fn stores_return(instruction: &Instruction, ret: usize) -> bool {
+    match instruction.pointer_operand() {
+        Some(idx) => {
+            instruction.operands[idx].value == ret as isize
+                && instruction.operands[2] == relative(0)
+        }
+        None => false,
+    }
+}
+
+/// A function, made of the blocks reachable from its entry without following calls.
+struct Function {
+    entry: usize,
+    blocks: BTreeSet<usize>,
+    /// The offset of the relative base from its value on entry, at the start of each block (if it's known).
+    offsets: BTreeMap<usize, Option<isize>>,
+    /// The size of the stack frame allocated on entry.
+    frame: Option<isize>,
+    params: usize,
+}
+
+/// Returns the addresses a block can continue at, within its function.
+fn successors(exit: Exit) -> Vec<usize> {
+    match exit {
+        Exit::Goto(next) => vec![next],
+        Exit::Branch { taken, next } => vec![taken, next],
+        Exit::Call { ret, .. } => vec![ret],
+        Exit::Indirect { next } => next.into_iter().collect(),
+        Exit::Return | Exit::Halt | Exit::End => vec![],
+    }
+}
+
+/// Returns the immediate value a relative base adjustment moves the base by, if it's known.
+fn adjustment(instruction: &Instruction) -> Option<Option<isize>> {
+    if instruction.opcode != Opcode::AdjustRelativeBase {
+        return None;
+    }
+    let operand = instruction.operands[0];
+    Some(Some(operand.value).filter(|_| operand.mode == Mode::Immediate))
+}
+
+/// The state of the decompiler, while emitting the code of a function.
+struct Emitter<'a> {
+    cfg: &'a Cfg,
+    shapes: &'a BTreeMap<usize, Shape>,
+    functions: &'a BTreeMap<usize, Function>,
+    function: &'a Function,
+    /// The immediate post-dominator of every block.
+    ipdom: BTreeMap<usize, usize>,
+    /// The loop headers, with the blocks of their loop.
+    loops: BTreeMap<usize, BTreeSet<usize>>,
+    /// The headers and exits of the loops being emitted, innermost last.
+    nesting: Vec<(usize, Option<usize>)>,
+    emitted: BTreeSet<usize>,
+    gotos: BTreeSet<usize>,
+    /// The emitted lines, along with their indentation (labels are placeholders, until gotos are known).
+    lines: Vec<(usize, Line)>,
+}
+
+enum Line {
+    Label(usize),
+    Text(String),
+}
+
+impl Line {
+    fn text(&self) -> Option<&str> {
+        match self {
+            Line::Text(text) => Some(text),
+            Line::Label(_) => None,
+        }
+    }
+}
+
+impl<'a> Emitter<'a> {
+    fn new(
+        cfg: &'a Cfg,
+        shapes: &'a BTreeMap<usize, Shape>,
+        functions: &'a BTreeMap<usize, Function>,
+        function: &'a Function,
+    ) -> Emitter<'a> {
+        let succ = |block: &usize| successors(shapes[block].exit);
+
+        // dominators, to find loops.
+        let mut dom: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for &block in function.blocks.iter() {
+            let set = if block == function.entry {
+                Some(block).into_iter().collect()
+            } else {
+                function.blocks.clone()
+            };
+            dom.insert(block, set);
+        }
+        let mut preds: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for block in function.blocks.iter() {
+            for next in succ(block) {
+                preds.entry(next).or_default().push(*block);
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in function.blocks.iter().filter(|&&it| it != function.entry) {
+                let mut set: Option<BTreeSet<usize>> = None;
+                for pred in preds.get(&block).into_iter().flatten() {
+                    set = Some(match set {
+                        Some(set) => set.intersection(&dom[pred]).copied().collect(),
+                        None => dom[pred].clone(),
+                    });
+                }
+                let mut set = set.unwrap_or_default();
+                set.insert(block);
+                if set != dom[&block] {
+                    dom.insert(block, set);
+                    changed = true;
+                }
+            }
+        }
+        let mut loops: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for &block in function.blocks.iter() {
+            for header in succ(&block) {
+                if !dom[&block].contains(&header) {
+                    continue;
+                }
+                // a back edge, the loop is made of the blocks reaching it without going through the header.
+                let body = loops.entry(header).or_default();
+                body.insert(header);
+                let mut queue = vec![block];
+                while let Some(block) = queue.pop() {
+                    if body.insert(block) {
+                        queue.extend(preds.get(&block).into_iter().flatten().copied());
+                    }
+                }
+            }
+        }
+
+        // post-dominators, to find where both sides of a branch join.
+        let mut pdom: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        let mut all = function.blocks.clone();
+        all.insert(EXIT);
+        for &block in all.iter() {
+            let set = if block == EXIT {
+                Some(EXIT).into_iter().collect()
+            } else {
+                all.clone()
+            };
+            pdom.insert(block, set);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in function.blocks.iter().rev() {
+                let mut next = succ(&block);
+                if next.is_empty() {
+                    next.push(EXIT);
+                }
+                let mut set: Option<BTreeSet<usize>> = None;
+                for next in next {
+                    set = Some(match set {
+                        Some(set) => set.intersection(&pdom[&next]).copied().collect(),
+                        None => pdom[&next].clone(),
+                    });
+                }
+                let mut set = set.unwrap_or_default();
+                set.insert(block);
+                if set != pdom[&block] {
+                    pdom.insert(block, set);
+                    changed = true;
+                }
+            }
+        }
+        let mut ipdom = BTreeMap::new();
+        for &block in function.blocks.iter() {
+            let strict: BTreeSet<usize> = pdom[&block]
+                .iter()
+                .copied()
+                .filter(|&it| it != block)
+                .collect();
+            let immediate = strict
+                .iter()
+                .copied()
+                .find(|candidate| pdom[candidate].len() == strict.len());
+            if let Some(immediate) = immediate.filter(|&it| it != EXIT) {
+                ipdom.insert(block, immediate);
+            }
+        }
+
+        Emitter {
+            cfg,
+            shapes,
+            functions,
+            function,
+            ipdom,
+            loops,
+            nesting: Vec::new(),
+            emitted: BTreeSet::new(),
+            gotos: BTreeSet::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    fn text(&mut self, indent: usize, text: String) {
+        self.lines.push((indent, Line::Text(text)));
+    }
+
+    /// Returns the name of the stack slot at the given offset from the relative base on entry,
+    /// or `None` if the slot is too far away to be named.
+    fn slot(&self, slot: isize, offset: isize) -> Option<String> {
+        let params = self.function.params as isize;
+        let name = match slot {
+            slot if slot > offset => format!("out{}", slot.checked_sub(offset)?),
+            slot if slot == offset => "ret".to_string(),
+            slot if slot < 0 => format!("up{}", slot.checked_neg()?),
+            0 => "ret_addr".to_string(),
+            slot if slot <= params => format!("arg{}", slot),
+            slot => format!("local{}", slot),
+        };
+        Some(name)
+    }
+
+    fn operand(&self, operand: Operand, offset: Option<isize>) -> String {
+        let slot = offset.and_then(|offset| {
+            let slot = offset.checked_add(operand.value)?;
+            self.slot(slot, offset)
+        });
+        match (operand.mode, slot) {
+            (Mode::Immediate, _) => operand.value.to_string(),
+            (Mode::Position, _) => format!("mem[{}]", operand.value),
+            (Mode::Relative, Some(slot)) => slot,
+            (Mode::Relative, None) => format!("rb[{}]", operand.value),
+        }
+    }
+
+    /// Returns the pseudo-code of an instruction that isn't part of a recognized idiom.
+    fn statement(&self, instruction: &Instruction, offset: Option<isize>) -> String {
+        let ops: Vec<String> = instruction
+            .operands
+            .iter()
+            .map(|&operand| self.operand(operand, offset))
+            .collect();
+        let (lhs, rhs) = (instruction.operands.first(), instruction.operands.get(1));
+        let immediate = |operand: Option<&Operand>, value: isize| {
+            operand
+                == Some(&Operand {
+                    mode: Mode::Immediate,
+                    value,
+                })
+        };
+        match instruction.opcode {
+            Opcode::Add if immediate(rhs, 0) => format!("{} = {};", ops[2], ops[0]),
+            Opcode::Add if immediate(lhs, 0) => format!("{} = {};", ops[2], ops[1]),
+            Opcode::Add => match rhs
+                .filter(|rhs| rhs.mode == Mode::Immediate && rhs.value < 0)
+                .and_then(|rhs| rhs.value.checked_neg())
+            {
+                Some(value) => format!("{} = {} - {};", ops[2], ops[0], value),
+                None => format!("{} = {} + {};", ops[2], ops[0], ops[1]),
+            },
+            Opcode::Multiply if immediate(rhs, 1) => format!("{} = {};", ops[2], ops[0]),
+            Opcode::Multiply if immediate(lhs, 1) => format!("{} = {};", ops[2], ops[1]),
+            Opcode::Multiply if immediate(rhs, -1) => format!("{} = -{};", ops[2], ops[0]),
+            Opcode::Multiply if immediate(lhs, -1) => format!("{} = -{};", ops[2], ops[1]),
+            Opcode::Multiply => format!("{} = {} * {};", ops[2], ops[0], ops[1]),
+            Opcode::LessThan => format!("{} = {} < {};", ops[2], ops[0], ops[1]),
+            Opcode::EqualTo => format!("{} = {} == {};", ops[2], ops[0], ops[1]),
+            Opcode::Input => format!("{} = input();", ops[0]),
+            Opcode::Output => format!("output({});", ops[0]),
+            Opcode::AdjustRelativeBase => format!("rb += {};", ops[0]),
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => format!("goto *{};", ops[1]),
+            Opcode::Halt => "halt();".to_string(),
+        }
+    }
+
+    /// Returns the condition under which the conditional jump ending a block is taken.
+    fn condition(&self, block: &BasicBlock, offset: Option<isize>, negate: bool) -> String {
+        let (_, last) = block.last();
+        let cond = self.operand(last.operands[0], offset);
+        if (last.opcode == Opcode::JumpIfTrue) != negate {
+            cond
+        } else {
+            format!("!{}", cond)
+        }
+    }
+
+    /// Emits the code starting at a block, until reaching the `stop` block.
+    fn emit(&mut self, mut address: usize, stop: Option<usize>, indent: usize) {
+        loop {
+            if Some(address) == stop {
+                return;
+            }
+            if let Some(&(header, exit)) = self.nesting.last() {
+                if address == header {
+                    self.text(indent, "continue;".to_string());
+                    return;
+                }
+                if Some(address) == exit {
+                    self.text(indent, "break;".to_string());
+                    return;
+                }
+            }
+            if self.emitted.contains(&address) || !self.function.blocks.contains(&address) {
+                self.gotos.insert(address);
+                self.text(indent, format!("goto {};", label(address)));
+                return;
+            }
+            let next = match self.loops.get(&address) {
+                Some(body) => {
+                    let exit = self.loop_exit(address, body);
+                    self.nesting.push((address, exit));
+                    self.r#loop(address, exit, indent);
+                    self.nesting.pop();
+                    exit
+                }
+                None => self.block(address, indent),
+            };
+            match next {
+                Some(next) => address = next,
+                None => return,
+            }
+        }
+    }
+
+    /// Emits a loop, starting at its header.
+    /// Headers only testing whether to leave the loop make a `while` loop, the other ones an endless loop.
+    fn r#loop(&mut self, header: usize, exit: Option<usize>, indent: usize) {
+        let block = match self.cfg.block_containing(header) {
+            Some(block) => block,
+            None => {
+                self.block(header, indent);
+                return;
+            }
+        };
+        let test = match self.shapes[&header].exit {
+            Exit::Branch { taken, next } if block.instructions.len() == 1 => match exit {
+                Some(exit) if exit == taken => Some((next, true)),
+                Some(exit) if exit == next => Some((taken, false)),
+                _ => None,
+            },
+            _ => None,
+        };
+        match test {
+            Some((body, negate)) => {
+                self.emitted.insert(header);
+                self.lines.push((indent, Line::Label(header)));
+                let offset = self.function.offsets.get(&header).copied().flatten();
+                let cond = self.condition(block, offset, negate);
+                self.text(indent, format!("while ({}) {{", cond));
+                self.emit(body, None, indent + 1);
+            }
+            None => {
+                let opening = self.lines.len();
+                self.text(indent, "loop {".to_string());
+                if let Some(next) = self.block(header, indent + 1) {
+                    self.emit(next, None, indent + 1);
+                }
+                if let Some(cond) = self.trailing_test(opening, indent) {
+                    self.lines.truncate(self.lines.len() - 4);
+                    self.lines[opening].1 = Line::Text("do {".to_string());
+                    self.text(indent, format!("}} while ({});", cond));
+                    return;
+                }
+            }
+        }
+        // continuing at the end of the body is implied.
+        if let Some((_, Line::Text(text))) = self.lines.last() {
+            if text == "continue;" {
+                self.lines.pop();
+            }
+        }
+        self.text(indent, "}".to_string());
+    }
+
+    /// Returns the condition of a loop whose body opened at the given line ends with
+    /// `if (cond) { continue; } break;`, which makes it a `do while` loop.
+    /// Loops continuing from anywhere else don't qualify, as `continue` would then skip to the condition.
+    fn trailing_test(&self, opening: usize, indent: usize) -> Option<String> {
+        let body = &self.lines[opening + 1..];
+        if body.len() < 4 {
+            return None;
+        }
+        let (body, tail) = body.split_at(body.len() - 4);
+        let indents: Vec<usize> = tail.iter().map(|&(indent, _)| indent).collect();
+        let texts: Vec<&str> = tail.iter().filter_map(|(_, line)| line.text()).collect();
+        match texts.as_slice() {
+            [test, "continue;", "}", "break;"]
+                if indents == [indent + 1, indent + 2, indent + 1, indent + 1]
+                    && !body
+                        .iter()
+                        .any(|(_, line)| line.text() == Some("continue;")) =>
+            {
+                let cond = test.strip_prefix("if (")?.strip_suffix(") {")?;
+                Some(cond.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns where execution continues after leaving a loop, if it can.
+    fn loop_exit(&self, header: usize, body: &BTreeSet<usize>) -> Option<usize> {
+        if let Some(&join) = self.ipdom.get(&header) {
+            if !body.contains(&join) {
+                return Some(join);
+            }
+        }
+        body.iter()
+            .flat_map(|block| successors(self.shapes[block].exit))
+            .find(|next| !body.contains(next))
+    }
+
+    /// Emits a single block and its exit, returns the address to continue with (if any).
+    fn block(&mut self, address: usize, indent: usize) -> Option<usize> {
+        self.emitted.insert(address);
+        self.lines.push((indent, Line::Label(address)));
+        let (block, shape) = match (
+            self.cfg.block_containing(address),
+            self.shapes.get(&address),
+        ) {
+            (Some(block), Some(&shape)) => (block, shape),
+            _ => {
+                self.text(indent, format!("goto {}; // not code", label(address)));
+                return None;
+            }
+        };
+        let mut offset = self.function.offsets.get(&address).copied().flatten();
+        let (_, last) = block.last();
+        let len = block.instructions.len();
+        let body_end = if last.jump_operand().is_some() || last.opcode == Opcode::Halt {
+            len - 1
+        } else {
+            len
+        };
+        for (idx, (_, instruction)) in block.instructions[..body_end].iter().enumerate() {
+            let prologue =
+                address == self.function.entry && idx == 0 && self.function.frame.is_some();
+            if let Some(amount) = adjustment(instruction) {
+                let hidden = prologue || Some(idx) == shape.epilogue;
+                offset = offset.and_then(|offset| offset.checked_add(amount?));
+                if hidden {
+                    continue;
+                }
+            }
+            if Some(idx) == shape.ret_store {
+                continue;
+            }
+            let statement = self.statement(instruction, offset);
+            self.text(indent, statement);
+        }
+
+        match shape.exit {
+            Exit::Goto(next) => Some(next),
+            Exit::End => {
+                self.text(indent, format!("goto {}; // not code", label(block.end)));
+                None
+            }
+            Exit::Halt => {
+                self.text(indent, "halt();".to_string());
+                None
+            }
+            Exit::Return => {
+                self.text(indent, "return;".to_string());
+                None
+            }
+            Exit::Indirect { next } => {
+                let statement = match certainty(last) {
+                    Some(_) => self.statement(last, offset),
+                    None => format!(
+                        "if ({}) {{ goto *{}; }}",
+                        self.condition(block, offset, false),
+                        self.operand(last.operands[1], offset)
+                    ),
+                };
+                self.text(indent, statement);
+                next
+            }
+            Exit::Call { target, ret } => {
+                let params = self.functions.get(&target).map_or(0, |it| it.params) as isize;
+                let args: Vec<String> = (1..=params)
+                    .map(|n| self.operand(relative(n), offset))
+                    .collect();
+                self.text(indent, format!("{}({});", name(target), args.join(", ")));
+                Some(ret)
+            }
+            Exit::Branch { taken, next } => {
+                let join = self.ipdom.get(&address).copied();
+                if Some(taken) == join {
+                    let cond = self.condition(block, offset, true);
+                    self.text(indent, format!("if ({}) {{", cond));
+                    self.emit(next, join, indent + 1);
+                } else if Some(next) == join {
+                    let cond = self.condition(block, offset, false);
+                    self.text(indent, format!("if ({}) {{", cond));
+                    self.emit(taken, join, indent + 1);
+                } else {
+                    let cond = self.condition(block, offset, false);
+                    self.text(indent, format!("if ({}) {{", cond));
+                    self.emit(taken, join, indent + 1);
+                    self.text(indent, "} else {".to_string());
+                    self.emit(next, join, indent + 1);
+                    if let Some((_, Line::Text(text))) = self.lines.last() {
+                        if text == "} else {" {
+                            self.lines.pop();
+                        }
+                    }
+                }
+                self.text(indent, "}".to_string());
+                join
+            }
+        }
+    }
+
+    /// Renders the emitted lines, keeping only the labels that are jumped to.
+    fn finish(self, out: &mut String) {
+        for (indent, line) in self.lines {
+            match line {
+                Line::Label(address) if self.gotos.contains(&address) => {
+                    writeln!(out, "{}{}:", "    ".repeat(indent), label(address)).unwrap()
+                }
+                Line::Label(_) => {}
+                Line::Text(text) => writeln!(out, "{}{}", "    ".repeat(indent + 1), text).unwrap(),
+            }
+        }
+    }
+}
+
+/// Returns the name of the function starting at an address.
+fn name(address: usize) -> String {
+    if address == 0 {
+        "main".to_string()
+    } else {
+        format!("f{:04}", address)
+    }
+}
+
+/// Decompiles a program into C-like pseudo-code.
+///
+/// Functions are recognized from the usual calling sequence: the caller stores the return address under the
+/// relative base (arguments going right above it) and jumps to the callee, which allocates its stack frame with
+/// `arb`, and eventually releases it and jumps back to the stored address.
+/// Stack slots get named after their role (`arg`, `local`, `out` for the arguments of the next call),
+/// and loops and if/else statements are recovered from the control flow, falling back to `goto` when they can't.
+pub fn decompile(program: &[isize]) -> String {
+    let cfg = Cfg::new(program);
+    let shapes: BTreeMap<usize, Shape> = cfg
+        .blocks
+        .iter()
+        .map(|block| (block.start, shape(block, &cfg)))
+        .collect();
+
+    // every call target is a function, and the number of its parameters is the number of slots above
+    // the return address written by its callers.
+    let mut entries: BTreeMap<usize, usize> = BTreeMap::new();
+    if shapes.contains_key(&0) {
+        entries.insert(0, 0);
+    }
+    for block in cfg.blocks.iter() {
+        if let Exit::Call { target, .. } = shapes[&block.start].exit {
+            // slots out of reach of an `isize` are left out.
+            let mut delta = Some(0isize);
+            let mut writes = Vec::new();
+            for (_, instruction) in block.instructions.iter() {
+                if let Some(amount) = adjustment(instruction) {
+                    delta = delta.and_then(|delta| delta.checked_add(amount.unwrap_or(0)));
+                }
+                let destination = instruction.operands.last().copied();
+                if let Some(destination) = destination.filter(|_| instruction.opcode.writes()) {
+                    if destination.mode == Mode::Relative {
+                        writes.push(delta.and_then(|delta| delta.checked_add(destination.value)));
+                    }
+                }
+            }
+            let params = writes
+                .into_iter()
+                .filter_map(|slot| slot?.checked_sub(delta?))
+                .filter(|&slot| slot > 0)
+                .max()
+                .unwrap_or(0);
+            let entry = entries.entry(target).or_insert(0);
+            *entry = (*entry).max(params as usize);
+        }
+    }
+
+    let mut functions = BTreeMap::new();
+    for (&entry, &params) in entries.iter() {
+        let block = cfg.block_containing(entry).expect("entries start blocks");
+        let frame = match block.instructions[0].1.operands.first() {
+            Some(operand)
+                if block.instructions[0].1.opcode == Opcode::AdjustRelativeBase
+                    && operand.mode == Mode::Immediate
+                    && operand.value > 0
+                    && entry != 0 =>
+            {
+                Some(operand.value)
+            }
+            _ => None,
+        };
+
+        let mut blocks = BTreeSet::new();
+        let mut offsets: BTreeMap<usize, Option<isize>> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((entry, Some(0)));
+        while let Some((address, mut offset)) = queue.pop_front() {
+            if let Some(&known) = offsets.get(&address) {
+                if known == offset || known.is_none() {
+                    continue;
+                }
+                // reached with conflicting offsets, the offset isn't known from here on.
+                offset = None;
+            }
+            let block = match cfg.block_containing(address) {
+                Some(block) if block.start == address => block,
+                _ => continue,
+            };
+            blocks.insert(address);
+            offsets.insert(address, offset);
+            let mut end = offset;
+            for (_, instruction) in block.instructions.iter() {
+                if let Some(amount) = adjustment(instruction) {
+                    end = end.and_then(|end| end.checked_add(amount?));
+                }
+            }
+            for next in successors(shapes[&address].exit) {
+                queue.push_back((next, end));
+            }
+        }
+
+        functions.insert(
+            entry,
+            Function {
+                entry,
+                blocks,
+                offsets,
+                frame,
+                params,
+            },
+        );
+    }
+
+    let mut out = String::new();
+    for (idx, function) in functions.values().enumerate() {
+        if idx > 0 {
+            writeln!(out).unwrap();
+        }
+        if let Some(frame) = function.frame {
+            writeln!(out, "// frame of {} cells", frame).unwrap();
+        }
+        let params: Vec<String> = (1..=function.params).map(|n| format!("arg{}", n)).collect();
+        writeln!(out, "fn {}({}) {{", name(function.entry), params.join(", ")).unwrap();
+        let mut emitter = Emitter::new(&cfg, &shapes, &functions, function);
+        emitter.emit(function.entry, None, 0);
+        emitter.finish(&mut out);
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
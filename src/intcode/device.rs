@@ -0,0 +1,181 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Write;
+use std::io;
+use std::rc::Rc;
+use std::sync::mpsc;
+
+/// A device providing input values to a machine.
+pub trait Source<W = isize> {
+    /// Returns the next input value, or `None` if there isn't any available.
+    fn read(&mut self) -> Option<W>;
+}
+
+/// A device receiving the output values of a machine.
+pub trait Sink<W = isize> {
+    fn write(&mut self, value: W);
+}
+
+/// Values are consumed from the front.
+impl<W> Source<W> for VecDeque<W> {
+    fn read(&mut self) -> Option<W> {
+        self.pop_front()
+    }
+}
+
+impl<W> Sink<W> for VecDeque<W> {
+    fn write(&mut self, value: W) {
+        self.push_back(value);
+    }
+}
+
+impl<W> Sink<W> for Vec<W> {
+    fn write(&mut self, value: W) {
+        self.push(value);
+    }
+}
+
+/// Only the last value is kept.
+impl<W> Sink<W> for Option<W> {
+    fn write(&mut self, value: W) {
+        self.replace(value);
+    }
+}
+
+impl<W, F: FnMut() -> Option<W>> Source<W> for F {
+    fn read(&mut self) -> Option<W> {
+        self()
+    }
+}
+
+impl<W, F: FnMut(W)> Sink<W> for F {
+    fn write(&mut self, value: W) {
+        self(value)
+    }
+}
+
+/// A source pulling values from an iterator.
+#[derive(Debug, Clone)]
+pub struct Iter<I>(pub I);
+
+impl<W, I: Iterator<Item = W>> Source<W> for Iter<I> {
+    fn read(&mut self) -> Option<W> {
+        self.0.next()
+    }
+}
+
+/// Blocks until a value is sent, there is none left once every sender is gone.
+impl<W> Source<W> for mpsc::Receiver<W> {
+    fn read(&mut self) -> Option<W> {
+        self.recv().ok()
+    }
+}
+
+/// Values sent once the receiver is gone are dropped.
+impl<W> Sink<W> for mpsc::Sender<W> {
+    fn write(&mut self, value: W) {
+        let _ = self.send(value);
+    }
+}
+
+/// An unbounded queue shared by its clones, writing to one of them makes the value available to all of them.
+/// Unlike channels, pipes never block, which makes them fit for machines taking turns on a single thread.
+#[derive(Debug)]
+pub struct Pipe<W = isize>(Rc<RefCell<VecDeque<W>>>);
+
+impl<W> Pipe<W> {
+    pub fn new() -> Pipe<W> {
+        Pipe(Rc::default())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+impl<W> Default for Pipe<W> {
+    fn default() -> Pipe<W> {
+        Pipe::new()
+    }
+}
+
+/// The clone shares the queue of the original pipe.
+impl<W> Clone for Pipe<W> {
+    fn clone(&self) -> Pipe<W> {
+        Pipe(Rc::clone(&self.0))
+    }
+}
+
+impl<W> Source<W> for Pipe<W> {
+    fn read(&mut self) -> Option<W> {
+        self.0.borrow_mut().pop_front()
+    }
+}
+
+impl<W> Sink<W> for Pipe<W> {
+    fn write(&mut self, value: W) {
+        self.0.borrow_mut().push_back(value);
+    }
+}
+
+/// Reads one value per line from the standard input, until the end of the input or an invalid line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stdin;
+
+impl Source for Stdin {
+    fn read(&mut self) -> Option<isize> {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).ok()?;
+        line.trim().parse().ok()
+    }
+}
+
+/// Prints every value on its own line to the standard output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stdout;
+
+impl<W: fmt::Display> Sink<W> for Stdout {
+    fn write(&mut self, value: W) {
+        println!("{}", value);
+    }
+}
+
+/// A source providing the character codes of an ASCII text.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Ascii {
+    bytes: VecDeque<u8>,
+}
+
+impl Ascii {
+    pub fn new(text: &str) -> Ascii {
+        Ascii {
+            bytes: text.bytes().collect(),
+        }
+    }
+
+    /// Queues more text to be read.
+    pub fn push_str(&mut self, text: &str) {
+        self.bytes.extend(text.bytes());
+    }
+}
+
+impl Source for Ascii {
+    fn read(&mut self) -> Option<isize> {
+        self.bytes.pop_front().map(isize::from)
+    }
+}
+
+/// ASCII codes are appended as characters, other values are written in decimal on their own line.
+impl Sink for String {
+    fn write(&mut self, value: isize) {
+        match value {
+            0..=127 => self.push(value as u8 as char),
+            _ => writeln!(self, "{}", value).expect("writing to a `String` can't fail"),
+        }
+    }
+}
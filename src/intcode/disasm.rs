@@ -0,0 +1,290 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::{Mode, Opcode};
+
+/// The number of values per line in data directives.
+const DATA_WIDTH: usize = 8;
+
+/// A decoded instruction parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Operand {
+    pub mode: Mode,
+    pub value: isize,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Immediate => write!(f, "#{}", self.value),
+            Mode::Relative if self.value < 0 => write!(f, "rb{}", self.value),
+            Mode::Relative => write!(f, "rb+{}", self.value),
+        }
+    }
+}
+
+/// A decoded instruction, along with its parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Decodes the instruction at the given address.
+    /// Returns `None` if the cells there don't form a valid instruction in its canonical encoding
+    /// (unknown opcode, unknown modes, superfluous mode digits, immediate destination or truncated parameters).
+    pub fn decode(memory: &[isize], address: usize) -> Option<Instruction> {
+        let instr = *memory.get(address)?;
+        if instr < 0 {
+            return None;
+        }
+        let opcode = Opcode::try_from((instr % 100) as usize).ok()?;
+        let mut modes = instr / 100;
+        let operands = (1..=opcode.arity())
+            .map(|n| {
+                let mode = Mode::try_from((modes % 10) as usize).ok()?;
+                modes /= 10;
+                let value = *memory.get(address + n)?;
+                Some(Operand { mode, value })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let instruction = Instruction { opcode, operands };
+        let writes_immediate = opcode.writes()
+            && instruction.operands.last().map(|operand| operand.mode) == Some(Mode::Immediate);
+        if modes != 0 || writes_immediate {
+            return None;
+        }
+        Some(instruction)
+    }
+
+    /// Returns the number of cells occupied by this instruction.
+    pub fn size(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    /// Encodes this instruction back into memory cells.
+    pub fn encode(&self) -> Vec<isize> {
+        let modes = self
+            .operands
+            .iter()
+            .rev()
+            .fold(0, |acc, operand| acc * 10 + operand.mode.code());
+        let instr = modes * 100 + self.opcode.code();
+        std::iter::once(instr)
+            .chain(self.operands.iter().map(|operand| operand.value))
+            .collect()
+    }
+
+    /// Returns whether execution can continue with the next instruction in memory.
+    pub fn falls_through(&self) -> bool {
+        match (self.opcode, self.operands.first()) {
+            (Opcode::Halt, _) => false,
+            (Opcode::JumpIfTrue, Some(cond)) => !(cond.mode == Mode::Immediate && cond.value != 0),
+            (Opcode::JumpIfFalse, Some(cond)) => !(cond.mode == Mode::Immediate && cond.value == 0),
+            _ => true,
+        }
+    }
+
+    /// Returns the index of the operand holding the target of this jump, if it is one.
+    pub fn jump_operand(&self) -> Option<usize> {
+        match self.opcode {
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Returns the target of this jump, if it is one and the target is known statically.
+    pub fn jump_target(&self) -> Option<usize> {
+        let operand = self.operands[self.jump_operand()?];
+        match operand.mode {
+            Mode::Immediate => usize::try_from(operand.value).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the index of the operand holding a code pointer being stored, if it looks like one.
+    /// This recognizes the `add #ptr, #0, rb+n` and `mul #ptr, #1, rb+n` idioms used to push return addresses.
+    pub fn pointer_operand(&self) -> Option<usize> {
+        let identity = match self.opcode {
+            Opcode::Add => 0,
+            Opcode::Multiply => 1,
+            _ => return None,
+        };
+        let (lhs, rhs, dst) = (self.operands[0], self.operands[1], self.operands[2]);
+        if dst.mode != Mode::Relative {
+            return None;
+        }
+        match (lhs.mode, rhs.mode) {
+            (Mode::Immediate, Mode::Immediate) if rhs.value == identity => Some(0),
+            (Mode::Immediate, Mode::Immediate) if lhs.value == identity => Some(1),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.opcode.mnemonic())?;
+        for (idx, operand) in self.operands.iter().enumerate() {
+            let sep = if idx == 0 { " " } else { ", " };
+            write!(f, "{}{}", sep, operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// The contents of a line of a listing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Item {
+    /// An instruction reachable from the entry point.
+    Instruction(Instruction),
+    /// Cells that aren't reachable code.
+    Data(Vec<isize>),
+}
+
+/// A line of a listing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Line {
+    pub address: usize,
+    pub item: Item,
+}
+
+/// An annotated listing of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    pub lines: Vec<Line>,
+    /// The addresses that are targeted by a jump or stored as a code pointer.
+    pub labels: BTreeSet<usize>,
+}
+
+/// Returns the label name for the given address.
+pub fn label(address: usize) -> String {
+    format!("L{:04}", address)
+}
+
+impl Listing {
+    /// Returns the textual form of the given operand of an instruction, using a label if it refers to one.
+    fn operand(&self, instruction: &Instruction, idx: usize) -> String {
+        let operand = instruction.operands[idx];
+        let is_pointer =
+            Some(idx) == instruction.jump_operand() || Some(idx) == instruction.pointer_operand();
+        let target = usize::try_from(operand.value).ok();
+        match target {
+            Some(target)
+                if is_pointer
+                    && operand.mode == Mode::Immediate
+                    && self.labels.contains(&target) =>
+            {
+                format!("#{}", label(target))
+            }
+            _ => operand.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.lines.iter() {
+            if self.labels.contains(&line.address) {
+                writeln!(f, "{}:", label(line.address))?;
+            }
+            let text = match &line.item {
+                Item::Instruction(instruction) => {
+                    let operands = (0..instruction.operands.len())
+                        .map(|idx| self.operand(instruction, idx))
+                        .collect::<Vec<_>>();
+                    if operands.is_empty() {
+                        instruction.opcode.mnemonic().to_string()
+                    } else {
+                        format!("{} {}", instruction.opcode.mnemonic(), operands.join(", "))
+                    }
+                }
+                Item::Data(values) => {
+                    let values = values.iter().map(|value| value.to_string());
+                    format!("db {}", values.collect::<Vec<_>>().join(", "))
+                }
+            };
+            writeln!(f, "    {:<48} ; {:04}", text, line.address)?;
+        }
+        Ok(())
+    }
+}
+
+/// Disassembles a program, following its control flow from address 0 to tell code apart from data.
+/// Jumps with immediate targets and code pointers stored with immediate operands are followed,
+/// jumps with computed targets are not.
+pub fn disassemble(program: &[isize]) -> Listing {
+    let mut decoded: Vec<Option<Instruction>> = vec![None; program.len()];
+    let mut covered = vec![false; program.len()];
+    let mut labels = BTreeSet::new();
+
+    let mut queue = VecDeque::new();
+    queue.push_back(0);
+    while let Some(address) = queue.pop_front() {
+        if address >= program.len() || covered[address] {
+            continue;
+        }
+        let instruction = match Instruction::decode(program, address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        let span = address..address + instruction.size();
+        if covered[span.clone()].iter().any(|&covered| covered) {
+            continue;
+        }
+        for cell in covered[span].iter_mut() {
+            *cell = true;
+        }
+
+        if instruction.falls_through() {
+            queue.push_back(address + instruction.size());
+        }
+        let pointer = instruction
+            .pointer_operand()
+            .map(|idx| instruction.operands[idx].value)
+            .and_then(|value| usize::try_from(value).ok())
+            .filter(|&target| Instruction::decode(program, target).is_some());
+        for target in instruction.jump_target().into_iter().chain(pointer) {
+            labels.insert(target);
+            queue.push_back(target);
+        }
+        decoded[address] = Some(instruction);
+    }
+
+    let mut lines: Vec<Line> = Vec::new();
+    let mut address = 0;
+    while address < program.len() {
+        if let Some(instruction) = decoded[address].take() {
+            let len = instruction.size();
+            lines.push(Line {
+                address,
+                item: Item::Instruction(instruction),
+            });
+            address += len;
+            continue;
+        }
+        // extend the previous data directive, unless a label or the line width gets in the way.
+        match lines.last_mut() {
+            Some(Line {
+                item: Item::Data(values),
+                ..
+            }) if values.len() < DATA_WIDTH && !labels.contains(&address) => {
+                values.push(program[address])
+            }
+            _ => lines.push(Line {
+                address,
+                item: Item::Data(vec![program[address]]),
+            }),
+        }
+        address += 1;
+    }
+
+    // only keep labels that start a line, the others can't be placed in the listing.
+    let starts: BTreeSet<usize> = lines.iter().map(|line| line.address).collect();
+    labels.retain(|address| starts.contains(address));
+
+    Listing { lines, labels }
+}
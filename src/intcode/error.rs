@@ -0,0 +1,94 @@
+use std::error;
+use std::fmt;
+
+/// The reasons an Intcode program can die with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Error {
+    /// The instruction at `address` has an unknown opcode.
+    UnknownOpcode { address: usize, opcode: isize },
+    /// The n-th `parameter` (starting at 1) of the instruction at `address` has an unknown mode.
+    InvalidMode {
+        address: usize,
+        parameter: usize,
+        mode: isize,
+    },
+    /// The instruction at `address` tried to access (or jump to) a negative `target` address.
+    NegativeAddress { address: usize, target: isize },
+    /// The n-th `parameter` (starting at 1) of the instruction at `address` is a destination in immediate mode.
+    ImmediateWrite { address: usize, parameter: usize },
+    /// The input instruction at `address` found no more values to read.
+    InputStarved { address: usize },
+    /// The instruction at `address` tried to access a `target` address beyond the memory `limit`.
+    MemoryLimitExceeded {
+        address: usize,
+        target: usize,
+        limit: usize,
+    },
+    /// The instruction at `address` computed a value that doesn't fit in a word,
+    /// or used one too large to be an opcode, an address or an offset.
+    Overflow { address: usize },
+    /// The machine ran out of fuel before executing the instruction at `address`.
+    OutOfFuel { address: usize },
+    /// The machine ran out of time before executing the instruction at `address`, or while waiting for an input there.
+    TimedOut { address: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownOpcode { address, opcode } => {
+                write!(f, "unknown opcode {} at address {}", opcode, address)
+            }
+            Error::InvalidMode {
+                address,
+                parameter,
+                mode,
+            } => write!(
+                f,
+                "invalid mode {} for parameter {} of the instruction at address {}",
+                mode, parameter, address
+            ),
+            Error::NegativeAddress { address, target } => write!(
+                f,
+                "negative address {} accessed by the instruction at address {}",
+                target, address
+            ),
+            Error::ImmediateWrite { address, parameter } => write!(
+                f,
+                "parameter {} of the instruction at address {} is written to in immediate mode",
+                parameter, address
+            ),
+            Error::InputStarved { address } => write!(
+                f,
+                "no more input available for the instruction at address {}",
+                address
+            ),
+            Error::MemoryLimitExceeded {
+                address,
+                target,
+                limit,
+            } => write!(
+                f,
+                "address {} accessed by the instruction at address {} exceeds the memory limit of {} cells",
+                target, address, limit
+            ),
+            Error::Overflow { address } => write!(
+                f,
+                "arithmetic overflow in the instruction at address {}",
+                address
+            ),
+            Error::OutOfFuel { address } => write!(
+                f,
+                "ran out of fuel before the instruction at address {}",
+                address
+            ),
+            Error::TimedOut { address } => write!(
+                f,
+                "timed out before the instruction at address {}",
+                address
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {}
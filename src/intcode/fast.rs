@@ -0,0 +1,430 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use super::{Dense, Error, Memory, Opcode, Snapshot, State, Vm, MEMORY_LIMIT};
+
+/// The code executing an instruction, specialized for its opcode and the modes of its parameters.
+/// It gets the raw arguments of the instruction and is responsible for moving the instruction pointer.
+/// It returns `None` if the instruction fails, without having changed anything.
+type Handler = fn(&mut FastVm, &[isize; 3]) -> Option<State>;
+
+/// An instruction compiled into its handler.
+#[derive(Clone, Copy)]
+struct Compiled {
+    handler: Handler,
+    args: [isize; 3],
+    /// The number of parameters of the instruction.
+    arity: usize,
+}
+
+/// What every address holds until the instruction found there gets executed for the first time.
+const PENDING: Compiled = Compiled {
+    handler: FastVm::compile,
+    args: [0; 3],
+    arity: 0,
+};
+
+/// A way to read a parameter.
+trait Param {
+    fn load(vm: &FastVm, arg: isize) -> Option<isize>;
+}
+
+/// A way to address the parameter an instruction writes to.
+trait Target {
+    fn address(vm: &FastVm, arg: isize) -> Option<usize>;
+}
+
+/// The position mode, its arguments are checked once when compiling the instruction.
+struct Position;
+
+struct Immediate;
+
+struct Relative;
+
+impl Param for Position {
+    #[inline(always)]
+    fn load(vm: &FastVm, arg: isize) -> Option<isize> {
+        Some(vm.read(arg as usize))
+    }
+}
+
+impl Target for Position {
+    #[inline(always)]
+    fn address(_: &FastVm, arg: isize) -> Option<usize> {
+        Some(arg as usize)
+    }
+}
+
+impl Param for Immediate {
+    #[inline(always)]
+    fn load(_: &FastVm, arg: isize) -> Option<isize> {
+        Some(arg)
+    }
+}
+
+impl Param for Relative {
+    #[inline(always)]
+    fn load(vm: &FastVm, arg: isize) -> Option<isize> {
+        Some(vm.read(vm.check(vm.relative_base.checked_add(arg)?)?))
+    }
+}
+
+impl Target for Relative {
+    #[inline(always)]
+    fn address(vm: &FastVm, arg: isize) -> Option<usize> {
+        vm.check(vm.relative_base.checked_add(arg)?)
+    }
+}
+
+/// An arithmetic or comparison operation.
+trait Binary {
+    fn apply(lhs: isize, rhs: isize) -> isize;
+}
+
+struct Sum;
+
+struct Product;
+
+struct Less;
+
+struct Equal;
+
+impl Binary for Sum {
+    #[inline(always)]
+    fn apply(lhs: isize, rhs: isize) -> isize {
+        lhs.wrapping_add(rhs)
+    }
+}
+
+impl Binary for Product {
+    #[inline(always)]
+    fn apply(lhs: isize, rhs: isize) -> isize {
+        lhs.wrapping_mul(rhs)
+    }
+}
+
+impl Binary for Less {
+    #[inline(always)]
+    fn apply(lhs: isize, rhs: isize) -> isize {
+        (lhs < rhs) as isize
+    }
+}
+
+impl Binary for Equal {
+    #[inline(always)]
+    fn apply(lhs: isize, rhs: isize) -> isize {
+        (lhs == rhs) as isize
+    }
+}
+
+fn binary<O: Binary, A: Param, B: Param, C: Target>(
+    vm: &mut FastVm,
+    args: &[isize; 3],
+) -> Option<State> {
+    let value = O::apply(A::load(vm, args[0])?, B::load(vm, args[1])?);
+    let target = C::address(vm, args[2])?;
+    vm.store(target, value);
+    vm.ip += 4;
+    Some(State::Running)
+}
+
+fn input<A: Target>(vm: &mut FastVm, args: &[isize; 3]) -> Option<State> {
+    let target = A::address(vm, args[0])?;
+    match vm.inputs.pop_front() {
+        Some(value) => vm.store(target, value),
+        None => return Some(State::NeedsInput),
+    }
+    vm.ip += 2;
+    Some(State::Running)
+}
+
+fn output<A: Param>(vm: &mut FastVm, args: &[isize; 3]) -> Option<State> {
+    let value = A::load(vm, args[0])?;
+    vm.ip += 2;
+    Some(State::Output(value))
+}
+
+fn jump<const IF_TRUE: bool, A: Param, B: Param>(
+    vm: &mut FastVm,
+    args: &[isize; 3],
+) -> Option<State> {
+    let condition = A::load(vm, args[0])? != 0;
+    let target = B::load(vm, args[1])?;
+    if condition == IF_TRUE {
+        vm.ip = vm.check(target)?;
+    } else {
+        vm.ip += 3;
+    }
+    Some(State::Running)
+}
+
+fn adjust_relative_base<A: Param>(vm: &mut FastVm, args: &[isize; 3]) -> Option<State> {
+    vm.relative_base = vm.relative_base.checked_add(A::load(vm, args[0])?)?;
+    vm.ip += 2;
+    Some(State::Running)
+}
+
+fn halt(_: &mut FastVm, _: &[isize; 3]) -> Option<State> {
+    Some(State::Halted)
+}
+
+/// Evaluates an expression with `$name` being the parameter type matching a mode, if it's a valid one.
+macro_rules! with_param {
+    ($mode:expr, $name:ident => $body:expr) => {
+        match $mode {
+            0 => {
+                type $name = Position;
+                $body
+            }
+            1 => {
+                type $name = Immediate;
+                $body
+            }
+            2 => {
+                type $name = Relative;
+                $body
+            }
+            _ => None,
+        }
+    };
+}
+
+/// Same as `with_param`, for parameters that get written to.
+macro_rules! with_target {
+    ($mode:expr, $name:ident => $body:expr) => {
+        match $mode {
+            0 => {
+                type $name = Position;
+                $body
+            }
+            2 => {
+                type $name = Relative;
+                $body
+            }
+            _ => None,
+        }
+    };
+}
+
+fn binary_handler<O: Binary + 'static>(modes: &[isize; 3]) -> Option<Handler> {
+    with_param!(modes[0], A => with_param!(modes[1], B => with_target!(modes[2], C => {
+        Some(binary::<O, A, B, C> as Handler)
+    })))
+}
+
+fn jump_handler<const IF_TRUE: bool>(modes: &[isize; 3]) -> Option<Handler> {
+    with_param!(modes[0], A => with_param!(modes[1], B => Some(jump::<IF_TRUE, A, B> as Handler)))
+}
+
+/// Returns the handler of an instruction, if all the modes of its parameters are valid ones.
+fn handler(opcode: Opcode, modes: &[isize; 3]) -> Option<Handler> {
+    match opcode {
+        Opcode::Add => binary_handler::<Sum>(modes),
+        Opcode::Multiply => binary_handler::<Product>(modes),
+        Opcode::LessThan => binary_handler::<Less>(modes),
+        Opcode::EqualTo => binary_handler::<Equal>(modes),
+        Opcode::Input => with_target!(modes[0], A => Some(input::<A> as Handler)),
+        Opcode::Output => with_param!(modes[0], A => Some(output::<A> as Handler)),
+        Opcode::JumpIfTrue => jump_handler::<true>(modes),
+        Opcode::JumpIfFalse => jump_handler::<false>(modes),
+        Opcode::AdjustRelativeBase => {
+            with_param!(modes[0], A => Some(adjust_relative_base::<A> as Handler))
+        }
+        Opcode::Halt => Some(halt as Handler),
+    }
+}
+
+/// An Intcode machine compiling every instruction into threaded code the first time it gets executed.
+///
+/// Compiled instructions are cached per address and invalidated whenever one of their cells gets written to,
+/// so self-modifying programs behave exactly as they do on `Vm`.
+#[derive(Clone)]
+pub struct FastVm {
+    memory: Vec<isize>,
+    /// The compiled instruction at each address, only growing as far as instructions got compiled.
+    code: Vec<Compiled>,
+    /// Whether each cell belongs to an instruction that got compiled, as long as `code`.
+    compiled: Vec<bool>,
+    ip: usize,
+    relative_base: isize,
+    inputs: VecDeque<isize>,
+    outputs: VecDeque<isize>,
+}
+
+impl FastVm {
+    pub fn new(program: Vec<isize>) -> FastVm {
+        FastVm {
+            code: vec![PENDING; program.len()],
+            compiled: vec![false; program.len()],
+            memory: program,
+            ip: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+        }
+    }
+
+    /// Returns the address of the next instruction to execute.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Returns the current relative base.
+    pub fn relative_base(&self) -> isize {
+        self.relative_base
+    }
+
+    pub fn memory(&self) -> &[isize] {
+        &self.memory
+    }
+
+    pub fn into_memory(self) -> Vec<isize> {
+        self.memory
+    }
+
+    /// Queues a value to be consumed by the next input instructions.
+    pub fn push_input(&mut self, value: isize) {
+        self.inputs.push_back(value);
+    }
+
+    /// Returns all the output values buffered by `run`, emptying the buffer.
+    pub fn take_outputs(&mut self) -> Vec<isize> {
+        self.outputs.drain(..).collect()
+    }
+
+    /// Reads a memory cell, addresses that were never written to read as zero.
+    #[inline]
+    pub fn read(&self, address: usize) -> isize {
+        self.memory.get(address).copied().unwrap_or(0)
+    }
+
+    /// Writes a memory cell, invalidating the compiled instructions overlapping it.
+    pub fn write(&mut self, address: usize, value: isize) -> Result<(), Error> {
+        if address >= MEMORY_LIMIT {
+            return Err(Error::MemoryLimitExceeded {
+                address: self.ip,
+                target: address,
+                limit: MEMORY_LIMIT,
+            });
+        }
+        self.store(address, value);
+        Ok(())
+    }
+
+    /// Writes a memory cell, the address is guaranteed to be below the limit.
+    #[inline]
+    fn store(&mut self, address: usize, value: isize) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        if self.compiled.get(address) == Some(&true) {
+            self.invalidate(address);
+        }
+    }
+
+    /// Drops the compiled instructions overlapping a memory cell, cells beyond `code` were never compiled.
+    #[cold]
+    fn invalidate(&mut self, address: usize) {
+        let end = self.code.len().min(address + 1);
+        let start = address.saturating_sub(3).min(end);
+        for compiled in self.code[start..end].iter_mut() {
+            *compiled = PENDING;
+        }
+        if let Some(cell) = self.compiled.get_mut(address) {
+            *cell = false;
+        }
+    }
+
+    /// Converts a value into an address, if it's a valid one.
+    #[inline]
+    fn check(&self, target: isize) -> Option<usize> {
+        usize::try_from(target)
+            .ok()
+            .filter(|&address| address < MEMORY_LIMIT)
+    }
+
+    /// Compiles the instruction under the instruction pointer, for the handler that does it.
+    /// Instructions whose parameters can't be resolved ahead of time (invalid modes or addresses) aren't compiled.
+    fn decode(&self) -> Option<Compiled> {
+        let instr = self.read(self.ip);
+        let opcode = Opcode::try_from((instr % 100) as usize).ok()?;
+        let mut modes = [0; 3];
+        let mut args = [0; 3];
+        let mut digits = instr / 100;
+        for n in 0..opcode.arity() {
+            modes[n] = digits % 10;
+            args[n] = self.read(self.ip + 1 + n);
+            if modes[n] == 0 && usize::try_from(args[n]).map_or(true, |it| it >= MEMORY_LIMIT) {
+                return None;
+            }
+            digits /= 10;
+        }
+        let handler = handler(opcode, &modes)?;
+        Some(Compiled {
+            handler,
+            args,
+            arity: opcode.arity(),
+        })
+    }
+
+    /// The handler of instructions that weren't compiled yet: it compiles and executes them.
+    fn compile(&mut self, _: &[isize; 3]) -> Option<State> {
+        let compiled = self.decode()?;
+        let end = self.ip + 1 + compiled.arity;
+        if end > self.code.len() {
+            self.code.resize(end, PENDING);
+            self.compiled.resize(end, false);
+        }
+        self.code[self.ip] = compiled;
+        for cell in self.compiled[self.ip..end].iter_mut() {
+            *cell = true;
+        }
+        (compiled.handler)(self, &compiled.args)
+    }
+
+    /// Returns the error raised by the instruction under the instruction pointer, which failed.
+    /// The instruction is executed again by a `Vm`, which reports the error just like it always does.
+    #[cold]
+    fn fault(&self) -> Error {
+        let snapshot = Snapshot {
+            ip: self.ip,
+            relative_base: self.relative_base,
+            inputs: self.inputs.iter().copied().collect(),
+            cells: Dense::new(self.memory.clone()).cells(),
+            ..Snapshot::default()
+        };
+        Vm::restore(&snapshot)
+            .and_then(|mut vm| vm.step())
+            .expect_err("instructions failing on a `FastVm` fail on a `Vm` as well")
+    }
+
+    /// Executes the instruction under the instruction pointer.
+    /// An input instruction without any queued value is left unexecuted, so it can be resumed once one is pushed.
+    #[inline]
+    pub fn step(&mut self) -> Result<State, Error> {
+        let compiled = self.code.get(self.ip).copied().unwrap_or(PENDING);
+        (compiled.handler)(self, &compiled.args).ok_or_else(|| self.fault())
+    }
+
+    /// Executes instructions until the machine needs an input, produces an output or halts.
+    pub fn run_until_io(&mut self) -> Result<State, Error> {
+        loop {
+            match self.step()? {
+                State::Running => {}
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Executes instructions until the machine needs an input or halts, buffering the values it outputs.
+    /// The returned state is either `State::NeedsInput` or `State::Halted`.
+    pub fn run(&mut self) -> Result<State, Error> {
+        loop {
+            match self.run_until_io()? {
+                State::Output(value) => self.outputs.push_back(value),
+                state => return Ok(state),
+            }
+        }
+    }
+}
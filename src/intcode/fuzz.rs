@@ -0,0 +1,377 @@
+use std::error;
+use std::fmt;
+use std::slice;
+
+use super::{
+    disassemble, Budget, Capped, Dense, Error, FastVm, Instruction, Memory, Mode, Opcode, Operand,
+    Sparse, State, Vm, MEMORY_LIMIT,
+};
+
+/// The number of instructions a generated program has at most.
+const MAX_INSTRUCTIONS: usize = 32;
+/// The number of data cells following the code of a generated program.
+const DATA_SIZE: usize = 16;
+/// How far beyond the end of a generated program position operands can point.
+const OVERREACH: usize = 16;
+/// The number of input values a generated case has at most.
+const MAX_INPUTS: usize = 4;
+/// One in this many instructions of a generated program is replaced by a raw cell.
+const RAW_ODDS: usize = 8;
+
+/// The number of instructions a case is run for by default, generated programs being free to loop forever.
+pub const FUZZ_FUEL: u64 = 10_000;
+
+const OPCODES: [Opcode; 10] = [
+    Opcode::Add,
+    Opcode::Multiply,
+    Opcode::Input,
+    Opcode::Output,
+    Opcode::JumpIfTrue,
+    Opcode::JumpIfFalse,
+    Opcode::LessThan,
+    Opcode::EqualTo,
+    Opcode::AdjustRelativeBase,
+    Opcode::Halt,
+];
+
+/// Draws values from the bytes provided by a fuzzer, as zeros once they run out.
+struct Bytes<'a>(slice::Iter<'a, u8>);
+
+impl Bytes<'_> {
+    fn byte(&mut self) -> u8 {
+        self.0.next().copied().unwrap_or(0)
+    }
+
+    /// Returns a number below `n`.
+    fn below(&mut self, n: usize) -> usize {
+        usize::from(self.byte()) % n
+    }
+
+    /// Returns a number between -128 and 127.
+    fn small(&mut self) -> isize {
+        isize::from(self.byte() as i8)
+    }
+}
+
+/// A program along with the inputs to run it with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Case {
+    pub program: Vec<isize>,
+    pub inputs: Vec<isize>,
+}
+
+impl Case {
+    /// Generates a case from arbitrary bytes, such as the ones provided by `cargo fuzz`.
+    ///
+    /// Programs are made of valid instructions followed by some data: jumps with immediate targets land on instructions,
+    /// and position operands point within the program or a bit beyond it.
+    /// Some instructions after the first one are replaced by a raw cell, which may hold an unknown opcode or invalid modes.
+    pub fn generate(data: &[u8]) -> Case {
+        let mut bytes = Bytes(data.iter());
+        let count = 1 + bytes.below(MAX_INSTRUCTIONS);
+        let opcodes: Vec<_> = (0..count)
+            .map(|idx| match bytes.below(RAW_ODDS) {
+                0 if idx > 0 => None,
+                _ => Some(OPCODES[bytes.below(OPCODES.len())]),
+            })
+            .collect();
+        let len = |opcode: &Option<Opcode>| 1 + opcode.map_or(0, Opcode::arity);
+        let starts: Vec<_> = opcodes
+            .iter()
+            .scan(0, |address, opcode| {
+                let start = *address;
+                *address += len(opcode);
+                Some(start)
+            })
+            .collect();
+        let size = starts[count - 1] + len(&opcodes[count - 1]) + DATA_SIZE;
+
+        let mut program = Vec::with_capacity(size);
+        for opcode in opcodes {
+            let opcode = match opcode {
+                Some(opcode) => opcode,
+                None => {
+                    program.push(raw(&mut bytes));
+                    continue;
+                }
+            };
+            let arity = opcode.arity();
+            let operands = (0..arity)
+                .map(|idx| {
+                    let writes = opcode.writes() && idx == arity - 1;
+                    let mode = match bytes.below(if writes { 2 } else { 3 }) {
+                        0 => Mode::Position,
+                        1 => Mode::Relative,
+                        _ => Mode::Immediate,
+                    };
+                    let is_target =
+                        matches!(opcode, Opcode::JumpIfTrue | Opcode::JumpIfFalse) && idx == 1;
+                    let value = match mode {
+                        Mode::Position => bytes.below(size + OVERREACH) as isize,
+                        Mode::Immediate if is_target => starts[bytes.below(count)] as isize,
+                        Mode::Immediate | Mode::Relative => bytes.small(),
+                    };
+                    Operand { mode, value }
+                })
+                .collect();
+            program.extend(Instruction { opcode, operands }.encode());
+        }
+        program.extend((0..DATA_SIZE).map(|_| bytes.small()));
+
+        let inputs = (0..bytes.below(MAX_INPUTS + 1))
+            .map(|_| bytes.small())
+            .collect();
+        Case { program, inputs }
+    }
+
+    /// Generates a case from a seed, for property tests to be reproducible without a fuzzer.
+    pub fn random(seed: u64) -> Case {
+        // xorshift64*
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        let bytes: Vec<_> = (0..256)
+            .map(|_| {
+                state ^= state >> 12;
+                state ^= state << 25;
+                state ^= state >> 27;
+                (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+            })
+            .collect();
+        Case::generate(&bytes)
+    }
+
+    /// Runs the case on the reference machine and on each backend, with the given amount of fuel,
+    /// failing with the first outcome that differs from the reference one.
+    pub fn check(&self, backends: &[&dyn Backend], fuel: u64) -> Result<(), Divergence> {
+        let expected = Engine::Vm.run(self, fuel);
+        for backend in backends {
+            let actual = backend.run(self, fuel);
+            if actual != expected {
+                return Err(Divergence {
+                    backend: backend.name(),
+                    expected: Box::new(expected),
+                    actual: Box::new(actual),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Shrinks a case as long as it keeps failing, to make a smaller reproducer.
+    /// Cells of the program get dropped or replaced by zeros, halt instructions or smaller values, and inputs get dropped.
+    pub fn shrink(&self, mut fails: impl FnMut(&Case) -> bool) -> Case {
+        let mut case = self.clone();
+        while let Some(smaller) = case.simplifications().into_iter().find(|case| fails(case)) {
+            case = smaller;
+        }
+        case
+    }
+
+    /// Returns the cases one step simpler than this one, the most promising ones first.
+    fn simplifications(&self) -> Vec<Case> {
+        let mut cases = Vec::new();
+        let len = self.program.len();
+        for keep in [len / 2, len.saturating_sub(1)] {
+            if keep < len {
+                let program = self.program[..keep].to_vec();
+                cases.push(Case {
+                    program,
+                    ..self.clone()
+                });
+            }
+        }
+        for idx in 0..len {
+            let mut case = self.clone();
+            case.program.remove(idx);
+            cases.push(case);
+        }
+        for idx in 0..self.inputs.len() {
+            let mut case = self.clone();
+            case.inputs.remove(idx);
+            cases.push(case);
+        }
+        for idx in 0..len {
+            let value = self.program[idx];
+            let simpler = [0, Opcode::Halt.code(), value / 2];
+            for &candidate in simpler
+                .iter()
+                .filter(|&&candidate| cost(candidate) < cost(value))
+            {
+                let mut case = self.clone();
+                case.program[idx] = candidate;
+                cases.push(case);
+            }
+        }
+        cases
+    }
+}
+
+/// Draws a raw cell, either a small value or an instruction with arbitrary opcode and mode digits.
+fn raw(bytes: &mut Bytes) -> isize {
+    match bytes.below(2) {
+        0 => bytes.small(),
+        _ => (0..3).fold(bytes.below(100), |instr, n| {
+            instr + bytes.below(10) * 10usize.pow(n + 2)
+        }) as isize,
+    }
+}
+
+/// Ranks the values of cells from the simplest to the most complex, zero and halt instructions first.
+/// Shrinking only ever makes cells simpler, so that it ends.
+fn cost(value: isize) -> usize {
+    match value {
+        0 => 0,
+        99 => 1,
+        _ => 2 + value.unsigned_abs(),
+    }
+}
+
+/// Formats the case in the syntax of the assembler, inputs being listed in a comment.
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inputs: Vec<_> = self.inputs.iter().map(|value| value.to_string()).collect();
+        writeln!(f, "; inputs: {}", inputs.join(", "))?;
+        write!(f, "{}", disassemble(&self.program))
+    }
+}
+
+/// How a run of a case went.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Outcome {
+    pub outputs: Vec<isize>,
+    /// `State::Halted`, `State::NeedsInput` if the inputs ran out, or the error that stopped the run.
+    pub end: Result<State, Error>,
+    /// The non-zero memory cells once the run ended.
+    pub memory: Vec<(usize, isize)>,
+}
+
+/// A way of running programs, to be compared with the reference machine.
+pub trait Backend {
+    fn name(&self) -> String;
+
+    /// Runs a case, executing at most `fuel` instructions before failing with `Error::OutOfFuel`.
+    fn run(&self, case: &Case, fuel: u64) -> Outcome;
+}
+
+/// The machines of this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Engine {
+    /// `Vm` with dense memory, the reference machine.
+    Vm,
+    /// `Vm` with sparse memory, capped to the limit of dense memory.
+    Sparse,
+    /// `FastVm`.
+    Fast,
+}
+
+impl Engine {
+    pub const ALL: [Engine; 3] = [Engine::Vm, Engine::Sparse, Engine::Fast];
+}
+
+impl Backend for Engine {
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn run(&self, case: &Case, fuel: u64) -> Outcome {
+        match self {
+            Engine::Vm => run_vm(Dense::new(case.program.clone()), case, fuel),
+            Engine::Sparse => {
+                let memory = Sparse::new(case.program.clone());
+                run_vm(Capped::new(memory, MEMORY_LIMIT), case, fuel)
+            }
+            Engine::Fast => run_fast(case, fuel),
+        }
+    }
+}
+
+fn run_vm<M: Memory<Word = isize>>(memory: M, case: &Case, fuel: u64) -> Outcome {
+    let mut vm = Vm::with_memory(memory);
+    vm.set_budget(Budget {
+        fuel: Some(fuel),
+        ..Budget::default()
+    });
+    for &input in case.inputs.iter() {
+        vm.push_input(input);
+    }
+    let end = vm.run();
+    Outcome {
+        outputs: vm.take_outputs(),
+        end,
+        memory: vm.memory().cells(),
+    }
+}
+
+/// `FastVm` has no budget, fuel is counted here the same way `Vm` does.
+fn run_fast(case: &Case, fuel: u64) -> Outcome {
+    let mut vm = FastVm::new(case.program.clone());
+    for &input in case.inputs.iter() {
+        vm.push_input(input);
+    }
+    let mut outputs = Vec::new();
+    let mut steps = 0;
+    let end = loop {
+        if steps >= fuel {
+            break Err(Error::OutOfFuel { address: vm.ip() });
+        }
+        match vm.step() {
+            Ok(State::Running) => {}
+            Ok(State::Output(value)) => outputs.push(value),
+            other => break other,
+        }
+        steps += 1;
+    };
+    let cells = vm.memory().iter().copied().enumerate();
+    Outcome {
+        outputs,
+        end,
+        memory: cells.filter(|&(_, value)| value != 0).collect(),
+    }
+}
+
+/// A case for which a backend didn't behave like the reference machine.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Divergence {
+    pub backend: String,
+    pub expected: Box<Outcome>,
+    pub actual: Box<Outcome>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (expected, actual) = (&self.expected, &self.actual);
+        write!(f, "{} diverges from the reference machine: ", self.backend)?;
+        if expected.outputs != actual.outputs {
+            let idx = first_difference(&expected.outputs, &actual.outputs);
+            write!(
+                f,
+                "expected output {} to be {:?}, got {:?}",
+                idx,
+                expected.outputs.get(idx),
+                actual.outputs.get(idx)
+            )
+        } else if expected.end != actual.end {
+            write!(
+                f,
+                "expected the run to end with {:?}, got {:?}",
+                expected.end, actual.end
+            )
+        } else {
+            let idx = first_difference(&expected.memory, &actual.memory);
+            write!(
+                f,
+                "expected the non-zero cell {:?}, got {:?}",
+                expected.memory.get(idx),
+                actual.memory.get(idx)
+            )
+        }
+    }
+}
+
+/// Returns the index of the first value that differs between two different slices.
+fn first_difference<T: PartialEq>(expected: &[T], actual: &[T]) -> usize {
+    (0..)
+        .find(|&idx| expected.get(idx) != actual.get(idx))
+        .expect("the slices are different")
+}
+
+impl error::Error for Divergence {}
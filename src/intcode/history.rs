@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+
+use super::{Event, Memory, Tracer, Vm, Word};
+
+/// A tracer keeping an undo log of the executed instructions, allowing to step machines backwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History<W = isize> {
+    events: VecDeque<Event<W>>,
+    capacity: usize,
+}
+
+impl<W: Word> History<W> {
+    /// Creates an history remembering every executed instruction.
+    pub fn new() -> History<W> {
+        History::with_capacity(usize::MAX)
+    }
+
+    /// Creates an history remembering only the last `capacity` executed instructions.
+    pub fn with_capacity(capacity: usize) -> History<W> {
+        History {
+            events: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns the remembered instructions, oldest first.
+    pub fn events(&self) -> &VecDeque<Event<W>> {
+        &self.events
+    }
+
+    /// Returns the last executed instruction.
+    pub fn last(&self) -> Option<&Event<W>> {
+        self.events.back()
+    }
+
+    /// Returns the last remembered instruction that wrote to the given address.
+    pub fn last_write(&self, address: usize) -> Option<&Event<W>> {
+        self.events
+            .iter()
+            .rev()
+            .find(|event| event.written.as_ref().map(|(target, _)| *target) == Some(address))
+    }
+
+    /// Reverts the last executed instruction on the machine, returning it.
+    pub fn undo<M: Memory<Word = W>>(&mut self, vm: &mut Vm<M>) -> Option<Event<W>> {
+        let event = self.events.pop_back()?;
+        vm.undo(&event);
+        Some(event)
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl<W: Word> Default for History<W> {
+    fn default() -> History<W> {
+        History::new()
+    }
+}
+
+impl<W: Word> Tracer<W> for History<W> {
+    fn trace(&mut self, event: &Event<W>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+    }
+}
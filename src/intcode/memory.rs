@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::Word;
+
+/// The maximum number of memory cells a dense memory is allowed to grow to.
+pub const MEMORY_LIMIT: usize = 1 << 24;
+
+/// The number of cells in a page of sparse memory.
+pub const PAGE_SIZE: usize = 1024;
+
+/// A storage backend for the memory of an Intcode machine.
+/// Cells that were never written to read as zero.
+pub trait Memory {
+    /// The type of the values held by the cells.
+    type Word: Word;
+
+    /// Returns the first address that can no longer be accessed.
+    fn limit(&self) -> usize;
+
+    /// Reads a memory cell.
+    fn read(&self, address: usize) -> Self::Word;
+
+    /// Writes a memory cell, the address is guaranteed to be below the limit.
+    fn write(&mut self, address: usize, value: Self::Word);
+
+    /// Returns every non-zero cell, ordered by address.
+    fn cells(&self) -> Vec<(usize, Self::Word)>;
+}
+
+/// A contiguous memory, growing on demand up to `MEMORY_LIMIT` cells.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Dense<W = isize>(Vec<W>);
+
+impl<W: Word> Dense<W> {
+    pub fn new(program: Vec<W>) -> Dense<W> {
+        Dense(program)
+    }
+
+    pub fn as_slice(&self) -> &[W] {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Vec<W> {
+        self.0
+    }
+}
+
+impl<W: Word> Memory for Dense<W> {
+    type Word = W;
+
+    fn limit(&self) -> usize {
+        MEMORY_LIMIT
+    }
+
+    fn read(&self, address: usize) -> W {
+        self.0.get(address).cloned().unwrap_or_default()
+    }
+
+    fn write(&mut self, address: usize, value: W) {
+        if address >= self.0.len() {
+            self.0.resize(address + 1, W::default());
+        }
+        self.0[address] = value;
+    }
+
+    fn cells(&self) -> Vec<(usize, W)> {
+        let cells = self.0.iter().cloned().enumerate();
+        cells.filter(|(_, value)| !value.is_zero()).collect()
+    }
+}
+
+/// A memory split into fixed-size pages, only allocated once written to.
+/// The whole address space is available, making it suitable for programs that scatter data far away.
+/// Clones share their pages until they get written to, which makes forking a machine cheap.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Sparse<W = isize> {
+    pages: HashMap<usize, Arc<Vec<W>>>,
+}
+
+impl<W: Word> Sparse<W> {
+    pub fn new(program: Vec<W>) -> Sparse<W> {
+        let mut memory = Sparse::default();
+        for (address, value) in program.into_iter().enumerate() {
+            memory.write(address, value);
+        }
+        memory
+    }
+
+    /// Returns the number of allocated pages.
+    pub fn pages(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl<W: Word> Memory for Sparse<W> {
+    type Word = W;
+
+    fn limit(&self) -> usize {
+        usize::MAX
+    }
+
+    fn read(&self, address: usize) -> W {
+        self.pages
+            .get(&(address / PAGE_SIZE))
+            .map_or_else(W::default, |page| page[address % PAGE_SIZE].clone())
+    }
+
+    fn write(&mut self, address: usize, value: W) {
+        let page = self
+            .pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| Arc::new(vec![W::default(); PAGE_SIZE]));
+        Arc::make_mut(page)[address % PAGE_SIZE] = value;
+    }
+
+    fn cells(&self) -> Vec<(usize, W)> {
+        let mut pages: Vec<_> = self.pages.iter().collect();
+        pages.sort_by_key(|&(&idx, _)| idx);
+        pages
+            .into_iter()
+            .flat_map(|(&idx, page)| {
+                let cells = page.iter().cloned().enumerate();
+                cells.map(move |(offset, value)| (idx * PAGE_SIZE + offset, value))
+            })
+            .filter(|(_, value)| !value.is_zero())
+            .collect()
+    }
+}
+
+/// Wraps another memory, restricting it to a hard maximum number of cells.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Capped<M> {
+    inner: M,
+    limit: usize,
+}
+
+impl<M: Memory> Capped<M> {
+    pub fn new(inner: M, limit: usize) -> Capped<M> {
+        Capped {
+            limit: limit.min(inner.limit()),
+            inner,
+        }
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M: Memory> Memory for Capped<M> {
+    type Word = M::Word;
+
+    fn limit(&self) -> usize {
+        self.limit
+    }
+
+    fn read(&self, address: usize) -> M::Word {
+        if address < self.limit {
+            self.inner.read(address)
+        } else {
+            M::Word::default()
+        }
+    }
+
+    fn write(&mut self, address: usize, value: M::Word) {
+        self.inner.write(address, value)
+    }
+
+    fn cells(&self) -> Vec<(usize, M::Word)> {
+        let mut cells = self.inner.cells();
+        cells.retain(|&(address, _)| address < self.limit);
+        cells
+    }
+}
@@ -0,0 +1,268 @@
+//! A complete Intcode virtual machine, shared by every day that needs one.
+
+use std::convert::TryFrom;
+
+use async_std::sync;
+
+mod asm;
+mod budget;
+mod bus;
+mod cfg;
+mod debugger;
+mod decompile;
+mod device;
+mod disasm;
+mod error;
+mod fast;
+mod fuzz;
+mod history;
+mod memory;
+mod network;
+mod scheduler;
+mod snapshot;
+mod symbolic;
+mod terminal;
+mod trace;
+mod transpile;
+mod varint;
+mod vm;
+mod word;
+
+pub use self::asm::{assemble, AsmError};
+pub use self::budget::Budget;
+pub use self::bus::{Bus, Packet, Traffic, MONITOR_ADDRESS};
+pub use self::cfg::{BasicBlock, Cfg, Edge, EdgeKind};
+pub use self::debugger::{Debugger, Stop, HISTORY_CAPACITY};
+pub use self::decompile::decompile;
+pub use self::device::{Ascii, Iter, Pipe, Sink, Source, Stdin, Stdout};
+pub use self::disasm::{disassemble, label, Instruction, Item, Line, Listing, Operand};
+pub use self::error::Error;
+pub use self::fast::FastVm;
+pub use self::fuzz::{Backend, Case, Divergence, Engine, Outcome, FUZZ_FUEL};
+pub use self::history::History;
+pub use self::memory::{Capped, Dense, Memory, Sparse, MEMORY_LIMIT, PAGE_SIZE};
+pub use self::network::{Network, NetworkError};
+pub use self::scheduler::Scheduler;
+pub use self::snapshot::Snapshot;
+pub use self::symbolic::{search, Expr, Polynomial, Symbolic, SymbolicError};
+pub use self::terminal::{Encoding, Message, Terminal};
+pub use self::trace::{Event, Profiler, TraceReader, TraceWriter, Tracer};
+pub use self::transpile::transpile;
+pub use self::vm::{InputPolicy, State, Vm};
+pub use self::word::{Checked, Word};
+pub use num_bigint::BigInt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    EqualTo,
+    AdjustRelativeBase,
+    Halt,
+}
+
+impl Opcode {
+    /// Returns the number of parameters taken by this instruction.
+    pub fn arity(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::EqualTo => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// Returns the numeric code of this instruction.
+    pub fn code(self) -> isize {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::EqualTo => 8,
+            Opcode::AdjustRelativeBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    /// Returns the assembly mnemonic of this instruction.
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Multiply => "mul",
+            Opcode::Input => "in",
+            Opcode::Output => "out",
+            Opcode::JumpIfTrue => "jt",
+            Opcode::JumpIfFalse => "jf",
+            Opcode::LessThan => "lt",
+            Opcode::EqualTo => "eq",
+            Opcode::AdjustRelativeBase => "arb",
+            Opcode::Halt => "hlt",
+        }
+    }
+
+    /// Returns the instruction with the given assembly mnemonic.
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        match mnemonic {
+            "add" => Some(Opcode::Add),
+            "mul" => Some(Opcode::Multiply),
+            "in" => Some(Opcode::Input),
+            "out" => Some(Opcode::Output),
+            "jt" => Some(Opcode::JumpIfTrue),
+            "jf" => Some(Opcode::JumpIfFalse),
+            "lt" => Some(Opcode::LessThan),
+            "eq" => Some(Opcode::EqualTo),
+            "arb" => Some(Opcode::AdjustRelativeBase),
+            "hlt" => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the last parameter of this instruction is a destination.
+    pub fn writes(self) -> bool {
+        matches!(
+            self,
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::EqualTo | Opcode::Input
+        )
+    }
+}
+
+impl TryFrom<usize> for Opcode {
+    type Error = ();
+    fn try_from(num: usize) -> Result<Opcode, Self::Error> {
+        match num {
+            1 => Ok(Opcode::Add),
+            2 => Ok(Opcode::Multiply),
+            3 => Ok(Opcode::Input),
+            4 => Ok(Opcode::Output),
+            5 => Ok(Opcode::JumpIfTrue),
+            6 => Ok(Opcode::JumpIfFalse),
+            7 => Ok(Opcode::LessThan),
+            8 => Ok(Opcode::EqualTo),
+            9 => Ok(Opcode::AdjustRelativeBase),
+            99 => Ok(Opcode::Halt),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl TryFrom<usize> for Mode {
+    type Error = ();
+    fn try_from(num: usize) -> Result<Mode, Self::Error> {
+        match num {
+            0 => Ok(Mode::Position),
+            1 => Ok(Mode::Immediate),
+            2 => Ok(Mode::Relative),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Mode {
+    /// Returns the numeric code of this mode.
+    pub fn code(self) -> isize {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+/// Parses a comma-separated Intcode program, as found in the puzzle inputs.
+pub fn parse(input: &str) -> Vec<isize> {
+    input
+        .trim()
+        .split(',')
+        .flat_map(|chunk| chunk.trim().parse().ok())
+        .collect()
+}
+
+/// Formats a program back into its comma-separated form.
+pub fn serialize(program: &[isize]) -> String {
+    let values: Vec<String> = program.iter().map(|value| value.to_string()).collect();
+    values.join(",")
+}
+
+pub struct Interpreter {
+    pub program: Vec<isize>,
+    /// The limits every run is subject to, unlimited unless set.
+    pub budget: Budget,
+    /// How `run_async` waits for inputs, blocking unless set.
+    pub input_policy: InputPolicy,
+}
+
+impl Interpreter {
+    pub fn new(program: Vec<isize>) -> Interpreter {
+        Interpreter {
+            program,
+            budget: Budget::default(),
+            input_policy: InputPolicy::default(),
+        }
+    }
+
+    /// Runs the program to completion, pulling inputs from the given iterator and passing every output to the given closure.
+    /// Returns the memory of the program once it halts.
+    pub fn run(
+        &self,
+        inputs: impl IntoIterator<Item = isize>,
+        output: impl FnMut(isize),
+    ) -> Result<Vec<isize>, Error> {
+        self.run_with(inputs, output)
+    }
+
+    /// Runs the program to completion like `run`, computing with the word type of the inputs.
+    pub fn run_with<W: Word>(
+        &self,
+        inputs: impl IntoIterator<Item = W>,
+        mut output: impl FnMut(W),
+    ) -> Result<Vec<W>, Error> {
+        self.run_io(&mut Iter(inputs.into_iter()), &mut output)
+    }
+
+    /// Runs the program to completion, reading inputs from a source and writing outputs to a sink.
+    /// Returns the memory of the program once it halts.
+    pub fn run_io<W: Word>(
+        &self,
+        input: &mut impl Source<W>,
+        output: &mut impl Sink<W>,
+    ) -> Result<Vec<W>, Error> {
+        let program = self.program.iter().map(|&value| W::from_isize(value));
+        let mut vm = Vm::with_memory(Dense::new(program.collect()));
+        vm.set_budget(self.budget);
+        match vm.run_io(input, output)? {
+            State::Halted => Ok(vm.into_memory().into_inner()),
+            _ => Err(Error::InputStarved { address: vm.ip() }),
+        }
+    }
+
+    /// Runs the program to completion, communicating through channels and waiting for inputs as the input policy says.
+    /// Returns the memory of the program once it halts, the policy giving up on an input being an `Error::InputStarved`.
+    pub async fn run_async(
+        &self,
+        input: sync::Receiver<isize>,
+        output: sync::Sender<isize>,
+    ) -> Result<Vec<isize>, Error> {
+        let mut vm = Vm::new(self.program.clone());
+        vm.set_budget(self.budget);
+        match vm.run_async(&input, &output, &self.input_policy).await? {
+            State::Halted => Ok(vm.into_memory().into_inner()),
+            _ => Err(Error::InputStarved { address: vm.ip() }),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::error;
+use std::fmt;
+
+use super::{Dense, Error, Memory, State, Vm};
+
+/// The reasons a network of machines can stop before all of them halt.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NetworkError {
+    /// The machine of the given `node` died.
+    Fault { node: usize, error: Error },
+    /// Every machine still running is waiting for an input that no other machine can provide.
+    Deadlock { blocked: Vec<usize> },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Fault { node, error } => write!(f, "node {}: {}", node, error),
+            NetworkError::Deadlock { blocked } => {
+                write!(
+                    f,
+                    "deadlock, nodes {:?} are all waiting for an input",
+                    blocked
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for NetworkError {}
+
+/// Machines wired together, the outputs of each one being sent as inputs to the machines it is linked to.
+///
+/// Machines are run in turn, each one until it needs an input or halts, so that runs are deterministic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network<M: Memory = Dense> {
+    nodes: Vec<Vm<M>>,
+    /// The nodes receiving the outputs of each node.
+    links: Vec<Vec<usize>>,
+    halted: Vec<bool>,
+    /// The outputs of each node, for the ones they are collected for.
+    collected: Vec<Option<Vec<M::Word>>>,
+}
+
+impl<M: Memory> Default for Network<M> {
+    fn default() -> Network<M> {
+        Network {
+            nodes: Vec::new(),
+            links: Vec::new(),
+            halted: Vec::new(),
+            collected: Vec::new(),
+        }
+    }
+}
+
+impl<M: Memory> Network<M> {
+    pub fn new() -> Network<M> {
+        Network::default()
+    }
+
+    /// Links the machines one after the other, the last one being left unlinked.
+    pub fn chain(vms: impl IntoIterator<Item = Vm<M>>) -> Network<M> {
+        let mut network = Network::new();
+        for vm in vms {
+            let node = network.add(vm);
+            if node > 0 {
+                network.link(node - 1, node);
+            }
+        }
+        network
+    }
+
+    /// Links the machines one after the other, the last one being linked back to the first one.
+    pub fn ring(vms: impl IntoIterator<Item = Vm<M>>) -> Network<M> {
+        let mut network = Network::chain(vms);
+        if !network.nodes.is_empty() {
+            network.link(network.nodes.len() - 1, 0);
+        }
+        network
+    }
+
+    /// Adds a machine to the network, returning its node number.
+    pub fn add(&mut self, vm: Vm<M>) -> usize {
+        self.nodes.push(vm);
+        self.links.push(Vec::new());
+        self.halted.push(false);
+        self.collected.push(None);
+        self.nodes.len() - 1
+    }
+
+    /// Sends the outputs of the node `from` to the node `to` as well.
+    pub fn link(&mut self, from: usize, to: usize) -> &mut Network<M> {
+        assert!(to < self.nodes.len(), "unknown node {}", to);
+        self.links[from].push(to);
+        self
+    }
+
+    /// Sends the outputs of the node `from` to each of the nodes `to`.
+    pub fn broadcast(&mut self, from: usize, to: &[usize]) -> &mut Network<M> {
+        for &node in to {
+            self.link(from, node);
+        }
+        self
+    }
+
+    /// Keeps the outputs of a node, to be read back with `outputs`.
+    pub fn collect(&mut self, node: usize) -> &mut Network<M> {
+        self.collected[node].get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Queues an input value for a node, such as a phase setting.
+    pub fn push_input(&mut self, node: usize, value: M::Word) -> &mut Network<M> {
+        self.nodes[node].push_input(value);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, node: usize) -> &Vm<M> {
+        &self.nodes[node]
+    }
+
+    pub fn node_mut(&mut self, node: usize) -> &mut Vm<M> {
+        &mut self.nodes[node]
+    }
+
+    pub fn is_halted(&self, node: usize) -> bool {
+        self.halted[node]
+    }
+
+    /// Returns the values output so far by a node whose outputs are collected.
+    pub fn outputs(&self, node: usize) -> &[M::Word] {
+        self.collected[node]
+            .as_deref()
+            .expect("the outputs of this node aren't collected")
+    }
+
+    /// Runs the machines until all of them halt.
+    /// Values sent to a halted machine are left in its input queue.
+    pub fn run(&mut self) -> Result<(), NetworkError> {
+        loop {
+            let mut progress = false;
+            for node in 0..self.nodes.len() {
+                if self.halted[node] {
+                    continue;
+                }
+                let state = self.nodes[node]
+                    .run()
+                    .map_err(|error| NetworkError::Fault { node, error })?;
+                self.halted[node] = state == State::Halted;
+
+                let outputs = self.nodes[node].take_outputs();
+                progress |= !outputs.is_empty();
+                for value in outputs {
+                    for &target in self.links[node].iter() {
+                        self.nodes[target].push_input(value.clone());
+                    }
+                    if let Some(collected) = &mut self.collected[node] {
+                        collected.push(value);
+                    }
+                }
+            }
+
+            let blocked: Vec<_> = (0..self.nodes.len())
+                .filter(|&node| !self.halted[node])
+                .collect();
+            if blocked.is_empty() {
+                return Ok(());
+            }
+            // no value was sent, so the blocked machines would keep waiting
+            if !progress {
+                return Err(NetworkError::Deadlock { blocked });
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use super::{Dense, Memory, NetworkError, Sink, Source, State, Vm};
+
+/// A machine scheduled along with the devices it reads from and writes to.
+struct Task<'a, M: Memory> {
+    vm: Vm<M>,
+    input: Box<dyn Source<M::Word> + 'a>,
+    output: Box<dyn Sink<M::Word> + 'a>,
+    state: State<M::Word>,
+}
+
+/// A cooperative executor running machines on the current thread.
+///
+/// Machines take turns in the order they were spawned, each one running until its source runs dry or it halts,
+/// so that runs are deterministic. Machines can talk to each other through `Pipe`s,
+/// and to the rest of the program through closures.
+pub struct Scheduler<'a, M: Memory = Dense> {
+    tasks: Vec<Task<'a, M>>,
+}
+
+impl<'a, M: Memory> Default for Scheduler<'a, M> {
+    fn default() -> Scheduler<'a, M> {
+        Scheduler { tasks: Vec::new() }
+    }
+}
+
+impl<'a, M: Memory> Scheduler<'a, M> {
+    pub fn new() -> Scheduler<'a, M> {
+        Scheduler::default()
+    }
+
+    /// Schedules a machine, returning its task number.
+    pub fn spawn(
+        &mut self,
+        vm: Vm<M>,
+        input: impl Source<M::Word> + 'a,
+        output: impl Sink<M::Word> + 'a,
+    ) -> usize {
+        self.tasks.push(Task {
+            vm,
+            input: Box::new(input),
+            output: Box::new(output),
+            state: State::Running,
+        });
+        self.tasks.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn vm(&self, task: usize) -> &Vm<M> {
+        &self.tasks[task].vm
+    }
+
+    /// Returns the state a task was left in by its last turn, `State::Running` if it never had one.
+    pub fn state(&self, task: usize) -> &State<M::Word> {
+        &self.tasks[task].state
+    }
+
+    /// Gives each machine still running a turn, returning whether any of them executed an instruction.
+    pub fn round(&mut self) -> Result<bool, NetworkError> {
+        let mut progress = false;
+        for (node, task) in self.tasks.iter_mut().enumerate() {
+            if task.state == State::Halted {
+                continue;
+            }
+            let steps = task.vm.steps();
+            task.state = task
+                .vm
+                .run_io(task.input.as_mut(), task.output.as_mut())
+                .map_err(|error| NetworkError::Fault { node, error })?;
+            progress |= task.vm.steps() != steps;
+        }
+        Ok(progress)
+    }
+
+    /// Runs rounds until every machine halts.
+    /// Fails with a deadlock as soon as a round goes by without any machine executing an instruction.
+    pub fn run(&mut self) -> Result<(), NetworkError> {
+        loop {
+            let progress = self.round()?;
+            let blocked: Vec<_> = (0..self.tasks.len())
+                .filter(|&task| self.tasks[task].state != State::Halted)
+                .collect();
+            if blocked.is_empty() {
+                return Ok(());
+            }
+            if !progress {
+                return Err(NetworkError::Deadlock { blocked });
+            }
+        }
+    }
+}
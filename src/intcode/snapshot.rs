@@ -0,0 +1,128 @@
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use super::varint::{read_signed, read_varint, write_signed, write_varint};
+
+/// The bytes every snapshot starts with.
+const MAGIC: &[u8; 5] = b"ICVM\x01";
+
+/// A frozen copy of the full state of a machine, as taken by `Vm::snapshot`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Snapshot {
+    pub ip: usize,
+    pub relative_base: isize,
+    /// The input values that weren't consumed yet.
+    pub inputs: Vec<isize>,
+    /// The output values that weren't taken yet.
+    pub outputs: Vec<isize>,
+    /// The non-zero memory cells, ordered by address.
+    pub cells: Vec<(usize, isize)>,
+}
+
+fn read_len(reader: &mut impl Read) -> io::Result<usize> {
+    let len = read_varint(reader)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+    Ok(len as usize)
+}
+
+fn write_values(writer: &mut impl Write, values: &[isize]) -> io::Result<()> {
+    write_varint(writer, values.len() as u64)?;
+    values
+        .iter()
+        .try_for_each(|&value| write_signed(writer, value))
+}
+
+fn read_values(reader: &mut impl Read) -> io::Result<Vec<isize>> {
+    let len = read_len(reader)?;
+    (0..len).map(|_| read_signed(reader)).collect()
+}
+
+impl Snapshot {
+    /// Writes the snapshot in its binary form.
+    ///
+    /// Memory is stored as runs of consecutive non-zero cells, every integer is encoded with a variable length.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let writer = &mut writer;
+        writer.write_all(MAGIC)?;
+        write_varint(writer, self.ip as u64)?;
+        write_signed(writer, self.relative_base)?;
+        write_values(writer, &self.inputs)?;
+        write_values(writer, &self.outputs)?;
+
+        let mut runs: Vec<(usize, Vec<isize>)> = Vec::new();
+        for &(address, value) in self.cells.iter() {
+            match runs.last_mut() {
+                Some((start, values)) if *start + values.len() == address => values.push(value),
+                _ => runs.push((address, vec![value])),
+            }
+        }
+        write_varint(writer, runs.len() as u64)?;
+        for (start, values) in runs {
+            write_varint(writer, start as u64)?;
+            write_values(writer, &values)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a snapshot written by `Snapshot::write_to`.
+    pub fn read_from(mut reader: impl Read) -> io::Result<Snapshot> {
+        let reader = &mut reader;
+        let mut magic = [0u8; 5];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an Intcode snapshot",
+            ));
+        }
+        let ip = read_len(reader)?;
+        let relative_base = read_signed(reader)?;
+        let inputs = read_values(reader)?;
+        let outputs = read_values(reader)?;
+
+        let mut cells = Vec::new();
+        for _ in 0..read_len(reader)? {
+            let start = read_len(reader)?;
+            let values = read_values(reader)?;
+            if start.checked_add(values.len()).is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "memory cells beyond the address space",
+                ));
+            }
+            let run = values.into_iter().enumerate();
+            cells.extend(run.map(|(idx, value)| (start + idx, value)));
+        }
+
+        Ok(Snapshot {
+            ip,
+            relative_base,
+            inputs,
+            outputs,
+            cells,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)
+            .expect("writing to a `Vec` can't fail");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Snapshot> {
+        Snapshot::read_from(bytes)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Snapshot> {
+        Snapshot::read_from(io::BufReader::new(fs::File::open(path)?))
+    }
+}
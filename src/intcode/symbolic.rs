@@ -0,0 +1,585 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::iter;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use super::{Error, Interpreter, Mode, Opcode, MEMORY_LIMIT};
+
+/// A value computed by a symbolic machine, as a tree of operations over symbols.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Const(isize),
+    Symbol(Rc<str>),
+    Add(Rc<Expr>, Rc<Expr>),
+    Mul(Rc<Expr>, Rc<Expr>),
+    Less(Rc<Expr>, Rc<Expr>),
+    Equal(Rc<Expr>, Rc<Expr>),
+    /// The memory cell at an address depending on symbols, as it was when read.
+    Load(Rc<Expr>),
+}
+
+impl Expr {
+    pub fn symbol(name: &str) -> Expr {
+        Expr::Symbol(name.into())
+    }
+
+    /// Returns the value of the expression, if it doesn't depend on any symbol.
+    pub fn as_const(&self) -> Option<isize> {
+        match *self {
+            Expr::Const(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn add(lhs: Expr, rhs: Expr) -> Expr {
+        match (lhs.as_const(), rhs.as_const()) {
+            (Some(lhs), Some(rhs)) if lhs.checked_add(rhs).is_some() => Expr::Const(lhs + rhs),
+            (Some(0), _) => rhs,
+            (_, Some(0)) => lhs,
+            _ => Expr::Add(Rc::new(lhs), Rc::new(rhs)),
+        }
+    }
+
+    fn mul(lhs: Expr, rhs: Expr) -> Expr {
+        match (lhs.as_const(), rhs.as_const()) {
+            (Some(lhs), Some(rhs)) if lhs.checked_mul(rhs).is_some() => Expr::Const(lhs * rhs),
+            (Some(0), _) | (_, Some(0)) => Expr::Const(0),
+            (Some(1), _) => rhs,
+            (_, Some(1)) => lhs,
+            _ => Expr::Mul(Rc::new(lhs), Rc::new(rhs)),
+        }
+    }
+
+    fn less(lhs: Expr, rhs: Expr) -> Expr {
+        match (lhs.as_const(), rhs.as_const()) {
+            (Some(lhs), Some(rhs)) => Expr::Const((lhs < rhs) as isize),
+            _ => Expr::Less(Rc::new(lhs), Rc::new(rhs)),
+        }
+    }
+
+    fn equal(lhs: Expr, rhs: Expr) -> Expr {
+        match (lhs.as_const(), rhs.as_const()) {
+            (Some(lhs), Some(rhs)) => Expr::Const((lhs == rhs) as isize),
+            _ => Expr::Equal(Rc::new(lhs), Rc::new(rhs)),
+        }
+    }
+
+    /// Expands the expression into a polynomial over its symbols.
+    /// Fails if it contains comparisons or loads that depend on symbols, or if a coefficient overflows.
+    pub fn expand(&self) -> Option<Polynomial> {
+        match self {
+            Expr::Const(value) => Some(Polynomial::constant(*value)),
+            Expr::Symbol(name) => Some(Polynomial::symbol(name)),
+            Expr::Add(lhs, rhs) => lhs.expand()?.add(&rhs.expand()?),
+            Expr::Mul(lhs, rhs) => lhs.expand()?.mul(&rhs.expand()?),
+            Expr::Less(..) | Expr::Equal(..) | Expr::Load(..) => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Add(lhs, rhs) => write!(f, "{} + {}", lhs, rhs),
+            Expr::Mul(lhs, rhs) => {
+                for (idx, factor) in [lhs, rhs].iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, " * ")?;
+                    }
+                    match factor.as_ref() {
+                        Expr::Add(..) => write!(f, "({})", factor)?,
+                        _ => write!(f, "{}", factor)?,
+                    }
+                }
+                Ok(())
+            }
+            Expr::Less(lhs, rhs) => write!(f, "({} < {})", lhs, rhs),
+            Expr::Equal(lhs, rhs) => write!(f, "({} == {})", lhs, rhs),
+            Expr::Load(address) => write!(f, "mem[{}]", address),
+        }
+    }
+}
+
+/// A product of symbols, each raised to a power, ordered by name.
+type Monomial = Vec<(Rc<str>, u32)>;
+
+/// A sum of monomials with integer coefficients.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, isize>,
+}
+
+impl Polynomial {
+    pub fn constant(value: isize) -> Polynomial {
+        let mut terms = BTreeMap::new();
+        if value != 0 {
+            terms.insert(Vec::new(), value);
+        }
+        Polynomial { terms }
+    }
+
+    pub fn symbol(name: &str) -> Polynomial {
+        let mut terms = BTreeMap::new();
+        terms.insert(vec![(name.into(), 1)], 1);
+        Polynomial { terms }
+    }
+
+    /// Returns the names of the symbols the polynomial depends on.
+    pub fn symbols(&self) -> Vec<Rc<str>> {
+        let names = self.terms.keys().flatten().map(|(name, _)| name.clone());
+        names.sorted().dedup().collect()
+    }
+
+    /// Returns the highest power the given symbol is raised to.
+    pub fn degree(&self, name: &str) -> u32 {
+        let powers = self.terms.keys().flatten();
+        let powers = powers.filter(|(symbol, _)| symbol.as_ref() == name);
+        powers.map(|&(_, power)| power).max().unwrap_or(0)
+    }
+
+    fn add(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut terms = self.terms.clone();
+        for (monomial, &coefficient) in other.terms.iter() {
+            let sum = terms.get(monomial).unwrap_or(&0).checked_add(coefficient)?;
+            if sum == 0 {
+                terms.remove(monomial);
+            } else {
+                terms.insert(monomial.clone(), sum);
+            }
+        }
+        Some(Polynomial { terms })
+    }
+
+    fn mul(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut product = Polynomial::default();
+        for (lhs, &a) in self.terms.iter() {
+            for (rhs, &b) in other.terms.iter() {
+                let mut powers: BTreeMap<Rc<str>, u32> = lhs.iter().cloned().collect();
+                for (name, power) in rhs.iter() {
+                    *powers.entry(name.clone()).or_insert(0) += power;
+                }
+                let mut term = Polynomial::default();
+                term.terms
+                    .insert(powers.into_iter().collect(), a.checked_mul(b)?);
+                product = product.add(&term)?;
+            }
+        }
+        Some(product)
+    }
+
+    /// Evaluates the polynomial, with the values of its symbols given by a function.
+    /// Returns `None` if the computation overflows.
+    pub fn evaluate(&self, value: impl Fn(&str) -> isize) -> Option<isize> {
+        self.terms
+            .iter()
+            .try_fold(0isize, |sum, (monomial, &coefficient)| {
+                let term = monomial
+                    .iter()
+                    .try_fold(coefficient, |term, (name, power)| {
+                        term.checked_mul(value(name).checked_pow(*power)?)
+                    })?;
+                sum.checked_add(term)
+            })
+    }
+
+    /// Finds values for the given symbols, within their ranges, for which the polynomial is equal to `target`.
+    ///
+    /// A symbol appearing in a single term, alone and with degree one, is solved for by division,
+    /// so that only the values of the other ones get enumerated.
+    /// Symbols that the polynomial doesn't depend on take the lowest value of their range.
+    pub fn solve(
+        &self,
+        target: isize,
+        domains: &[(&str, RangeInclusive<isize>)],
+    ) -> Option<Vec<isize>> {
+        let linear = domains.iter().enumerate().find_map(|(idx, (name, _))| {
+            let mut terms = self.terms.iter().filter(|(monomial, _)| {
+                monomial.iter().any(|(symbol, _)| symbol.as_ref() == *name)
+            });
+            match (terms.next(), terms.next()) {
+                (Some((monomial, &coefficient)), None)
+                    if monomial[..] == [(Rc::from(*name), 1)] =>
+                {
+                    Some((idx, monomial.clone(), coefficient))
+                }
+                _ => None,
+            }
+        });
+        let mut rest = self.clone();
+        if let Some((_, monomial, _)) = &linear {
+            rest.terms.remove(monomial);
+        }
+
+        let enumerated = domains.iter().enumerate().map(|(idx, (name, range))| {
+            if linear.as_ref().map(|&(solved, _, _)| solved) == Some(idx) || rest.degree(name) == 0
+            {
+                *range.start()..=*range.start()
+            } else {
+                range.clone()
+            }
+        });
+        for mut values in enumerated.multi_cartesian_product() {
+            let value = |name: &str| {
+                let idx = domains.iter().position(|(symbol, _)| *symbol == name);
+                idx.map_or(0, |idx| values[idx])
+            };
+            let sum = match rest.evaluate(value) {
+                Some(sum) => sum,
+                None => continue,
+            };
+            match linear {
+                Some((idx, _, coefficient)) => {
+                    let remainder = match target.checked_sub(sum) {
+                        Some(remainder) => remainder,
+                        None => continue,
+                    };
+                    let quotient = match remainder.checked_rem(coefficient) {
+                        Some(0) => remainder.checked_div(coefficient),
+                        _ => None,
+                    };
+                    match quotient {
+                        Some(quotient) if domains[idx].1.contains(&quotient) => {
+                            values[idx] = quotient;
+                            return Some(values);
+                        }
+                        _ => continue,
+                    }
+                }
+                None if sum == target => return Some(values),
+                None => {}
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        for (idx, (monomial, &coefficient)) in self.terms.iter().rev().enumerate() {
+            let sign = if coefficient < 0 { "-" } else { "+" };
+            match idx {
+                0 if coefficient < 0 => write!(f, "-")?,
+                0 => {}
+                _ => write!(f, " {} ", sign)?,
+            }
+            let mut factors = Vec::new();
+            if coefficient.abs() != 1 || monomial.is_empty() {
+                factors.push(coefficient.abs().to_string());
+            }
+            for (name, power) in monomial.iter() {
+                match power {
+                    1 => factors.push(name.to_string()),
+                    _ => factors.push(format!("{}^{}", name, power)),
+                }
+            }
+            write!(f, "{}", factors.join(" * "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The reasons a symbolic machine can stop before its program halts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolicError {
+    /// The jump at `address` may or may not be taken, depending on the value of `condition`.
+    Branch { address: usize, condition: Expr },
+    /// The instruction at `address` writes to, or jumps to, a `target` address depending on symbols.
+    Address { address: usize, target: Expr },
+    /// The instruction at `address` depends on symbols, either through its opcode, its modes or the relative base.
+    Instruction { address: usize },
+    /// The program fails, whatever the values of the symbols.
+    Fault(Error),
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::Branch { address, condition } => write!(
+                f,
+                "the jump at address {} depends on the value of {}",
+                address, condition
+            ),
+            SymbolicError::Address { address, target } => write!(
+                f,
+                "the instruction at address {} accesses the address {}, which isn't constant",
+                address, target
+            ),
+            SymbolicError::Instruction { address } => {
+                write!(f, "the instruction at address {} isn't constant", address)
+            }
+            SymbolicError::Fault(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for SymbolicError {}
+
+impl From<Error> for SymbolicError {
+    fn from(err: Error) -> SymbolicError {
+        SymbolicError::Fault(err)
+    }
+}
+
+/// An Intcode machine whose memory cells hold expressions, some of them being symbols standing for unknown values.
+///
+/// The program runs along a single path, so it stops as soon as control flow depends on symbols.
+/// Every value read by an input instruction is a new symbol, named `input1`, `input2` and so on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbolic {
+    memory: Vec<Expr>,
+    ip: usize,
+    relative_base: isize,
+    inputs: usize,
+    outputs: Vec<Expr>,
+}
+
+impl Symbolic {
+    pub fn new(program: &[isize]) -> Symbolic {
+        Symbolic {
+            memory: program.iter().map(|&value| Expr::Const(value)).collect(),
+            ip: 0,
+            relative_base: 0,
+            inputs: 0,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Replaces the value of a memory cell with a symbol.
+    pub fn symbol(&mut self, address: usize, name: &str) {
+        self.write(address, Expr::symbol(name))
+            .expect("symbols are placed within the memory limit");
+    }
+
+    /// Returns the expression held by a memory cell.
+    pub fn read(&self, address: usize) -> Expr {
+        self.memory.get(address).cloned().unwrap_or(Expr::Const(0))
+    }
+
+    /// Returns the expressions output so far.
+    pub fn outputs(&self) -> &[Expr] {
+        &self.outputs
+    }
+
+    fn write(&mut self, address: usize, value: Expr) -> Result<(), Error> {
+        if address >= MEMORY_LIMIT {
+            return Err(Error::MemoryLimitExceeded {
+                address: self.ip,
+                target: address,
+                limit: MEMORY_LIMIT,
+            });
+        }
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, Expr::Const(0));
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    /// Converts a constant value into an address, as accessed by the current instruction.
+    fn check(&self, target: isize) -> Result<usize, Error> {
+        let address = usize::try_from(target).map_err(|_| Error::NegativeAddress {
+            address: self.ip,
+            target,
+        })?;
+        if address >= MEMORY_LIMIT {
+            return Err(Error::MemoryLimitExceeded {
+                address: self.ip,
+                target: address,
+                limit: MEMORY_LIMIT,
+            });
+        }
+        Ok(address)
+    }
+
+    /// Returns the mode of the n-th parameter (starting at 1) of an instruction.
+    fn mode(&self, instr: isize, n: usize) -> Result<Mode, Error> {
+        let mode = (instr / 10isize.pow(n as u32 + 1)) % 10;
+        Mode::try_from(mode as usize).map_err(|_| Error::InvalidMode {
+            address: self.ip,
+            parameter: n,
+            mode,
+        })
+    }
+
+    /// Computes the address targeted by the n-th parameter of an instruction, as an expression.
+    fn address(&self, instr: isize, n: usize) -> Result<Expr, SymbolicError> {
+        let arg = self.read(self.ip + n);
+        match self.mode(instr, n)? {
+            Mode::Position => Ok(arg),
+            Mode::Relative => Ok(Expr::add(Expr::Const(self.relative_base), arg)),
+            Mode::Immediate => Err(Error::ImmediateWrite {
+                address: self.ip,
+                parameter: n,
+            }
+            .into()),
+        }
+    }
+
+    /// Computes the value of the n-th parameter of an instruction.
+    fn load(&self, instr: isize, n: usize) -> Result<Expr, SymbolicError> {
+        if self.mode(instr, n)? == Mode::Immediate {
+            return Ok(self.read(self.ip + n));
+        }
+        match self.address(instr, n)? {
+            Expr::Const(target) => Ok(self.read(self.check(target)?)),
+            target => Ok(Expr::Load(Rc::new(target))),
+        }
+    }
+
+    /// Computes the address written to by the n-th parameter of an instruction, which must be constant.
+    fn target(&self, instr: isize, n: usize) -> Result<usize, SymbolicError> {
+        match self.address(instr, n)? {
+            Expr::Const(target) => Ok(self.check(target)?),
+            target => Err(SymbolicError::Address {
+                address: self.ip,
+                target,
+            }),
+        }
+    }
+
+    /// Executes the instruction under the instruction pointer, returns whether the machine halted.
+    pub fn step(&mut self) -> Result<bool, SymbolicError> {
+        let address = self.ip;
+        let instr = self
+            .read(address)
+            .as_const()
+            .ok_or(SymbolicError::Instruction { address })?;
+        let opcode = usize::try_from(instr % 100)
+            .ok()
+            .and_then(|code| Opcode::try_from(code).ok())
+            .ok_or(Error::UnknownOpcode {
+                address,
+                opcode: instr,
+            })?;
+
+        match opcode {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::EqualTo => {
+                let (lhs, rhs) = (self.load(instr, 1)?, self.load(instr, 2)?);
+                let target = self.target(instr, 3)?;
+                let value = match opcode {
+                    Opcode::Add => Expr::add(lhs, rhs),
+                    Opcode::Multiply => Expr::mul(lhs, rhs),
+                    Opcode::LessThan => Expr::less(lhs, rhs),
+                    _ => Expr::equal(lhs, rhs),
+                };
+                self.write(target, value)?;
+            }
+            Opcode::Input => {
+                let target = self.target(instr, 1)?;
+                self.inputs += 1;
+                let name = format!("input{}", self.inputs);
+                self.write(target, Expr::symbol(&name))?;
+            }
+            Opcode::Output => {
+                let value = self.load(instr, 1)?;
+                self.outputs.push(value);
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let condition = self.load(instr, 1)?;
+                let condition = condition
+                    .as_const()
+                    .ok_or(SymbolicError::Branch { address, condition })?;
+                if (condition != 0) == (opcode == Opcode::JumpIfTrue) {
+                    let target = self.load(instr, 2)?;
+                    let target = target
+                        .as_const()
+                        .ok_or(SymbolicError::Address { address, target })?;
+                    self.ip = self.check(target)?;
+                    return Ok(false);
+                }
+            }
+            Opcode::AdjustRelativeBase => {
+                let offset = self.load(instr, 1)?;
+                let offset = offset
+                    .as_const()
+                    .ok_or(SymbolicError::Instruction { address })?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or(Error::Overflow { address })?;
+            }
+            Opcode::Halt => return Ok(true),
+        }
+        self.ip += 1 + opcode.arity();
+        Ok(false)
+    }
+
+    /// Runs the program until it halts.
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        while !self.step()? {}
+        Ok(())
+    }
+}
+
+/// Finds values for the given memory cells, within their ranges, for which the program halts with `target` at `address`.
+///
+/// The program is first run symbolically, so that the final value of the cell can be solved for as a polynomial.
+/// The values are enumerated, running the program for each of them, when control flow depends on the unknown cells
+/// or when no solution of the polynomial is confirmed by a concrete run.
+pub fn search(
+    program: &[isize],
+    unknowns: &[(usize, RangeInclusive<isize>)],
+    address: usize,
+    target: isize,
+) -> Option<Vec<isize>> {
+    let run = |values: &[isize]| {
+        let mut program = program.to_vec();
+        for (&(cell, _), &value) in unknowns.iter().zip(values) {
+            if cell >= program.len() {
+                program.resize(cell + 1, 0);
+            }
+            program[cell] = value;
+        }
+        let memory = Interpreter::new(program).run(iter::empty(), |_| {}).ok()?;
+        Some(memory.get(address).copied().unwrap_or(0))
+    };
+
+    let names: Vec<String> = unknowns
+        .iter()
+        .map(|(cell, _)| format!("mem{}", cell))
+        .collect();
+    let mut machine = Symbolic::new(program);
+    for ((cell, _), name) in unknowns.iter().zip(names.iter()) {
+        machine.symbol(*cell, name);
+    }
+    if machine.run().is_ok() {
+        let domains: Vec<(&str, RangeInclusive<isize>)> = names
+            .iter()
+            .zip(unknowns)
+            .map(|(name, (_, range))| (name.as_str(), range.clone()))
+            .collect();
+        let polynomial = machine.read(address).expand();
+        let known = |polynomial: &Polynomial| {
+            let symbols = polynomial.symbols();
+            symbols
+                .iter()
+                .all(|symbol| names.iter().any(|name| name == &**symbol))
+        };
+        if let Some(polynomial) = polynomial.filter(known) {
+            // the symbolic run doesn't catch faults depending on the unknown cells, such as negative addresses,
+            // so a solution is only trusted once a concrete run confirms it.
+            if let Some(values) = polynomial.solve(target, &domains) {
+                if run(&values) == Some(target) {
+                    return Some(values);
+                }
+            }
+        }
+    }
+
+    let values = unknowns.iter().map(|(_, range)| range.clone());
+    let candidates: Vec<Vec<isize>> = values.multi_cartesian_product().collect();
+    candidates
+        .into_par_iter()
+        .find_first(|values| run(values) == Some(target))
+}
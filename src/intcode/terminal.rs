@@ -0,0 +1,132 @@
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::num::ParseIntError;
+
+use super::{Dense, Error, Memory, State, Vm};
+
+/// How lines of text are translated to and from the values of a machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Input lines are sent as their character codes followed by a newline,
+    /// output values are decoded as characters, except for the ones out of the ASCII range.
+    Ascii,
+    /// Input lines hold a single value, and every output value makes its own line.
+    Numeric,
+}
+
+/// A line of output produced by a machine.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Message {
+    Text(String),
+    /// A value out of the ASCII range, passed through as is.
+    Value(isize),
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Text(text) => write!(f, "{}", text),
+            Message::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// An adapter talking to a machine one line of text at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terminal<M: Memory<Word = isize> = Dense> {
+    vm: Vm<M>,
+    encoding: Encoding,
+    /// The characters of the output line being decoded.
+    pending: String,
+}
+
+impl<M: Memory<Word = isize>> Terminal<M> {
+    pub fn new(vm: Vm<M>, encoding: Encoding) -> Terminal<M> {
+        Terminal {
+            vm,
+            encoding,
+            pending: String::new(),
+        }
+    }
+
+    pub fn vm(&self) -> &Vm<M> {
+        &self.vm
+    }
+
+    pub fn vm_mut(&mut self) -> &mut Vm<M> {
+        &mut self.vm
+    }
+
+    pub fn into_vm(self) -> Vm<M> {
+        self.vm
+    }
+
+    /// Queues a line of input, the trailing newline being optional.
+    /// Fails if the encoding is numeric and the line isn't a valid value.
+    pub fn send(&mut self, line: &str) -> Result<(), ParseIntError> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        match self.encoding {
+            Encoding::Ascii => {
+                for c in line.chars().chain(Some('\n')) {
+                    self.vm.push_input(c as isize);
+                }
+            }
+            Encoding::Numeric => self.vm.push_input(line.trim().parse()?),
+        }
+        Ok(())
+    }
+
+    /// Runs the machine until it needs an input or halts, returning the lines it produced.
+    /// A line of text left unfinished, such as a prompt, is returned as well.
+    pub fn run(&mut self) -> Result<(State, Vec<Message>), Error> {
+        let mut lines = Vec::new();
+        let state = loop {
+            let value = match self.vm.run_until_io()? {
+                State::Output(value) => value,
+                state => break state,
+            };
+            match (self.encoding, value) {
+                (Encoding::Ascii, 10) => lines.push(Message::Text(self.pending.split_off(0))),
+                (Encoding::Ascii, 0..=127) => self.pending.push(value as u8 as char),
+                _ => {
+                    if !self.pending.is_empty() {
+                        lines.push(Message::Text(self.pending.split_off(0)));
+                    }
+                    lines.push(Message::Value(value));
+                }
+            }
+        };
+        if !self.pending.is_empty() {
+            lines.push(Message::Text(self.pending.split_off(0)));
+        }
+        Ok((state, lines))
+    }
+
+    /// Runs the machine until it halts, reading a line of input whenever it needs some,
+    /// and writing the lines it produces as soon as they are complete.
+    pub fn interact(
+        &mut self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> Result<(), crate::Error> {
+        loop {
+            let (state, lines) = self.run()?;
+            for line in lines {
+                writeln!(output, "{}", line)?;
+            }
+            output.flush()?;
+            if state == State::Halted {
+                return Ok(());
+            }
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(Error::InputStarved {
+                    address: self.vm.ip(),
+                }
+                .into());
+            }
+            self.send(&line)?;
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+
+use super::varint::{read_signed, read_varint, write_signed, write_varint};
+use super::Opcode;
+
+/// The bytes every trace file starts with.
+const MAGIC: &[u8; 5] = b"ICTR\x02";
+
+/// An instruction that has been executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Event<W = isize> {
+    /// The address of the instruction.
+    pub address: usize,
+    pub opcode: Opcode,
+    /// The resolved parameters (only the first `opcode.arity()` are meaningful):
+    /// values for the ones that are read, addresses for the ones that are written to.
+    pub operands: [W; 3],
+    /// The address and the value written by the instruction, if any.
+    pub written: Option<(usize, W)>,
+    /// The value held by the written cell before the instruction (zero if nothing got written).
+    pub previous: W,
+}
+
+/// A hook called by the machine for every executed instruction, `W` being the word type of the machine.
+pub trait Tracer<W = isize> {
+    fn trace(&mut self, event: &Event<W>);
+}
+
+/// The tracer that doesn't do anything.
+impl<W> Tracer<W> for () {
+    fn trace(&mut self, _: &Event<W>) {}
+}
+
+impl<W, T: Tracer<W> + ?Sized> Tracer<W> for &mut T {
+    fn trace(&mut self, event: &Event<W>) {
+        (**self).trace(event)
+    }
+}
+
+/// Both tracers get called, in order.
+impl<W, A: Tracer<W>, B: Tracer<W>> Tracer<W> for (A, B) {
+    fn trace(&mut self, event: &Event<W>) {
+        self.0.trace(event);
+        self.1.trace(event);
+    }
+}
+
+/// A tracer recording every event into a compact binary trace.
+///
+/// Each record holds the address and the opcode of the instruction, followed by its resolved parameters
+/// and the written value along with the one it replaced (if any), all encoded as variable-length integers.
+pub struct TraceWriter<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<TraceWriter<W>> {
+        writer.write_all(MAGIC)?;
+        Ok(TraceWriter {
+            writer,
+            error: None,
+        })
+    }
+
+    fn record(&mut self, event: &Event) -> io::Result<()> {
+        write_varint(&mut self.writer, event.address as u64)?;
+        write_varint(&mut self.writer, event.opcode.code() as u64)?;
+        for &operand in event.operands[..event.opcode.arity()].iter() {
+            write_signed(&mut self.writer, operand)?;
+        }
+        if let Some((_, value)) = event.written {
+            write_signed(&mut self.writer, value)?;
+            write_signed(&mut self.writer, event.previous)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the trace, returning the underlying writer or the first error encountered while tracing.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Tracer for TraceWriter<W> {
+    fn trace(&mut self, event: &Event) {
+        if self.error.is_none() {
+            self.error = self.record(event).err();
+        }
+    }
+}
+
+/// An iterator over the events of a trace written by `TraceWriter`.
+pub struct TraceReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(mut reader: R) -> io::Result<TraceReader<R>> {
+        let mut magic = [0u8; 5];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an Intcode trace",
+            ));
+        }
+        Ok(TraceReader { reader })
+    }
+
+    fn record(&mut self) -> io::Result<Option<Event>> {
+        let address = match read_varint(&mut self.reader)? {
+            Some(address) => address as usize,
+            None => return Ok(None),
+        };
+        let code = read_varint(&mut self.reader)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        let opcode = Opcode::try_from(code as usize)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "unknown opcode in trace"))?;
+        let mut operands = [0; 3];
+        for operand in operands[..opcode.arity()].iter_mut() {
+            *operand = read_signed(&mut self.reader)?;
+        }
+        let (written, previous) = if opcode.writes() {
+            let target = operands[opcode.arity() - 1] as usize;
+            let value = read_signed(&mut self.reader)?;
+            (Some((target, value)), read_signed(&mut self.reader)?)
+        } else {
+            (None, 0)
+        };
+        Ok(Some(Event {
+            address,
+            opcode,
+            operands,
+            written,
+            previous,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.record().transpose()
+    }
+}
+
+/// A tracer counting how many times each instruction and each opcode got executed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Profiler {
+    hits: HashMap<usize, u64>,
+    opcodes: HashMap<Opcode, u64>,
+    total: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Returns the number of executions of the instructions, per address.
+    pub fn hits(&self) -> &HashMap<usize, u64> {
+        &self.hits
+    }
+
+    /// Returns the number of executions, per opcode.
+    pub fn opcodes(&self) -> &HashMap<Opcode, u64> {
+        &self.opcodes
+    }
+
+    /// Returns the total number of executed instructions.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the `count` most executed addresses, most executed first.
+    pub fn hottest(&self, count: usize) -> Vec<(usize, u64)> {
+        let mut hits: Vec<(usize, u64)> = self.hits.iter().map(|(&k, &v)| (k, v)).collect();
+        hits.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hits.truncate(count);
+        hits
+    }
+}
+
+impl<W> Tracer<W> for Profiler {
+    fn trace(&mut self, event: &Event<W>) {
+        *self.hits.entry(event.address).or_insert(0) += 1;
+        *self.opcodes.entry(event.opcode).or_insert(0) += 1;
+        self.total += 1;
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+
+        writeln!(f, "{} instructions executed", self.total)?;
+        writeln!(f)?;
+        writeln!(f, "per opcode:")?;
+        let mut opcodes: Vec<(Opcode, u64)> = self.opcodes.iter().map(|(&k, &v)| (k, v)).collect();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.code().cmp(&b.0.code())));
+        for (opcode, count) in opcodes {
+            let mnemonic = opcode.mnemonic();
+            writeln!(
+                f,
+                "    {:<4} {:>12} {:>6.2}%",
+                mnemonic,
+                count,
+                percent(count)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "hottest addresses:")?;
+        for (address, count) in self.hottest(20) {
+            writeln!(
+                f,
+                "    {:04} {:>12} {:>6.2}%",
+                address,
+                count,
+                percent(count)
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,398 @@
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+use super::{BasicBlock, Cfg, Mode, Opcode, Operand, MEMORY_LIMIT};
+
+/// The part of every transpiled program that doesn't depend on the program itself:
+/// the machine state, and the interpreter used for the code that couldn't be compiled ahead of time.
+static RUNTIME: &str = r#"use std::io;
+use std::io::Read;
+use std::process;
+
+/// The address returned by blocks once the program halted.
+const HALT: usize = usize::MAX;
+
+struct Machine {
+    memory: Vec<i64>,
+    rb: i64,
+    inputs: std::vec::IntoIter<i64>,
+    /// Whether each compiled block got written to, after which only the interpreter can run it.
+    dirty: Vec<bool>,
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1)
+}
+
+/// Converts a value into an address.
+#[inline]
+fn addr(value: i64) -> usize {
+    if value < 0 {
+        fail(format!("negative address {} accessed", value));
+    }
+    value as usize
+}
+
+impl Machine {
+    /// Computes `rb + offset` as an address.
+    #[inline]
+    fn rel(&self, offset: i64) -> usize {
+        addr(self.rb.checked_add(offset).unwrap_or_else(|| fail("arithmetic overflow in a relative address".to_string())))
+    }
+
+    /// Adds an offset to the relative base.
+    #[inline]
+    fn adjust(&mut self, offset: i64) {
+        self.rb = self.rb.checked_add(offset).unwrap_or_else(|| fail("arithmetic overflow in the relative base".to_string()));
+    }
+
+    #[inline]
+    fn get(&self, address: usize) -> i64 {
+        self.memory.get(address).copied().unwrap_or(0)
+    }
+
+    /// Writes a memory cell that isn't part of any compiled block.
+    #[inline]
+    fn set(&mut self, address: usize, value: i64) {
+        if address >= self.memory.len() {
+            if address >= MEMORY_LIMIT {
+                fail(format!("address {} exceeds the memory limit of {} cells", address, MEMORY_LIMIT));
+            }
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+    }
+
+    /// Writes a memory cell, returns whether it belonged to a compiled block (which can't be trusted anymore).
+    #[inline]
+    fn write(&mut self, address: usize, value: i64) -> bool {
+        self.set(address, value);
+        match block_containing(address) {
+            Some(block) => {
+                self.dirty[block] = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn input(&mut self) -> i64 {
+        self.inputs.next().unwrap_or_else(|| fail("no more input available".to_string()))
+    }
+
+    fn output(&mut self, value: i64) {
+        println!("{}", value);
+    }
+
+    fn mode(&self, ip: usize, n: u32) -> i64 {
+        (self.get(ip) / 10i64.pow(n + 1)) % 10
+    }
+
+    fn load(&self, ip: usize, n: u32) -> i64 {
+        let arg = self.get(ip + n as usize);
+        match self.mode(ip, n) {
+            0 => self.get(addr(arg)),
+            1 => arg,
+            2 => self.get(self.rel(arg)),
+            mode => fail(format!("invalid mode {} for parameter {} of the instruction at address {}", mode, n, ip)),
+        }
+    }
+
+    fn target(&self, ip: usize, n: u32) -> usize {
+        let arg = self.get(ip + n as usize);
+        match self.mode(ip, n) {
+            0 => addr(arg),
+            2 => self.rel(arg),
+            1 => fail(format!("parameter {} of the instruction at address {} is written to in immediate mode", n, ip)),
+            mode => fail(format!("invalid mode {} for parameter {} of the instruction at address {}", mode, n, ip)),
+        }
+    }
+
+    /// Interprets instructions from the given address until reaching a compiled block that can be trusted,
+    /// returns the address of that block.
+    fn interpret(&mut self, mut ip: usize) -> usize {
+        loop {
+            let instr = self.get(ip);
+            ip = match instr % 100 {
+                1 | 2 | 7 | 8 => {
+                    let (lhs, rhs) = (self.load(ip, 1), self.load(ip, 2));
+                    let target = self.target(ip, 3);
+                    let value = match instr % 100 {
+                        1 => lhs.wrapping_add(rhs),
+                        2 => lhs.wrapping_mul(rhs),
+                        7 => (lhs < rhs) as i64,
+                        _ => (lhs == rhs) as i64,
+                    };
+                    self.write(target, value);
+                    ip + 4
+                }
+                3 => {
+                    let target = self.target(ip, 1);
+                    let value = self.input();
+                    self.write(target, value);
+                    ip + 2
+                }
+                4 => {
+                    let value = self.load(ip, 1);
+                    self.output(value);
+                    ip + 2
+                }
+                5 | 6 => {
+                    let (cond, target) = (self.load(ip, 1), self.load(ip, 2));
+                    if (cond != 0) == (instr % 100 == 5) {
+                        addr(target)
+                    } else {
+                        ip + 3
+                    }
+                }
+                9 => {
+                    let offset = self.load(ip, 1);
+                    self.adjust(offset);
+                    ip + 2
+                }
+                99 => return HALT,
+                _ => fail(format!("unknown opcode {} at address {}", instr, ip)),
+            };
+            if let Some(block) = block_index(ip) {
+                if !self.dirty[block] {
+                    return ip;
+                }
+            }
+        }
+    }
+}
+"#;
+
+/// The entry point of every transpiled program, `{blocks}` being the number of blocks and `{dispatch}` the arms of their jump table.
+static MAIN: &str = r#"fn main() {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .unwrap_or_else(|err| fail(format!("can't read the input: {}", err)));
+    let inputs: Vec<i64> = input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.parse().unwrap_or_else(|_| fail(format!("invalid input `{}`", word))))
+        .collect();
+
+    let mut m = Machine {
+        memory: PROGRAM.to_vec(),
+        rb: 0,
+        inputs: inputs.into_iter(),
+        dirty: vec![false; {blocks}],
+    };
+    let mut pc = 0;
+    while pc != HALT {
+        pc = match pc {
+{dispatch}            _ => m.interpret(pc),
+        };
+    }
+}
+"#;
+
+/// Returns an expression computing the value of an operand.
+fn load(operand: Operand) -> String {
+    match operand.mode {
+        Mode::Immediate => operand.value.to_string(),
+        Mode::Position if operand.value >= 0 => format!("m.get({})", operand.value),
+        Mode::Position => format!("m.get(addr({}))", operand.value),
+        Mode::Relative => format!("m.get(m.rel({}))", operand.value),
+    }
+}
+
+/// Returns an expression computing the address an operand writes to.
+fn target(operand: Operand) -> String {
+    match operand.mode {
+        Mode::Position if operand.value >= 0 => operand.value.to_string(),
+        Mode::Relative => format!("m.rel({})", operand.value),
+        _ => format!("addr({})", operand.value),
+    }
+}
+
+/// Compiles a block into the body of a function returning the address to continue from.
+fn compile(block: &BasicBlock, cfg: &Cfg) -> String {
+    let mut body = String::new();
+    let mut tail = format!("{}", block.end);
+    for (address, instruction) in block.instructions.iter() {
+        let next = address + instruction.size();
+        let ops = &instruction.operands;
+        writeln!(body, "    // {:04}: {}", address, instruction).unwrap();
+        let value = match instruction.opcode {
+            Opcode::Add => Some(format!(
+                "i64::wrapping_add({}, {})",
+                load(ops[0]),
+                load(ops[1])
+            )),
+            Opcode::Multiply => Some(format!(
+                "i64::wrapping_mul({}, {})",
+                load(ops[0]),
+                load(ops[1])
+            )),
+            Opcode::LessThan => Some(format!("({} < {}) as i64", load(ops[0]), load(ops[1]))),
+            Opcode::EqualTo => Some(format!("({} == {}) as i64", load(ops[0]), load(ops[1]))),
+            Opcode::Input => Some("m.input()".to_string()),
+            Opcode::Output => {
+                writeln!(body, "    m.output({});", load(ops[0])).unwrap();
+                None
+            }
+            Opcode::AdjustRelativeBase => {
+                writeln!(body, "    m.adjust({});", load(ops[0])).unwrap();
+                None
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let never = ops[0].mode == Mode::Immediate && instruction.falls_through();
+                let target = match ops[1].mode {
+                    Mode::Immediate if ops[1].value >= 0 => ops[1].value.to_string(),
+                    Mode::Immediate => format!("addr({})", ops[1].value),
+                    // the target is still read when the jump is never taken, as that can fail.
+                    _ if never => {
+                        writeln!(body, "    {};", load(ops[1])).unwrap();
+                        String::new()
+                    }
+                    _ => {
+                        writeln!(body, "    let target = {};", load(ops[1])).unwrap();
+                        "addr(target)".to_string()
+                    }
+                };
+                let op = if instruction.opcode == Opcode::JumpIfTrue {
+                    "!="
+                } else {
+                    "=="
+                };
+                tail = match ops[0].mode {
+                    _ if never => next.to_string(),
+                    Mode::Immediate => target,
+                    _ => format!(
+                        "if {} {} 0 {{ {} }} else {{ {} }}",
+                        load(ops[0]),
+                        op,
+                        target,
+                        next
+                    ),
+                };
+                None
+            }
+            Opcode::Halt => {
+                tail = "HALT".to_string();
+                None
+            }
+        };
+        if let Some(value) = value {
+            let destination = *ops.last().expect("instructions writing have operands");
+            writeln!(body, "    let value = {};", value).unwrap();
+            let address = match destination.mode {
+                Mode::Position => usize::try_from(destination.value).ok(),
+                _ => None,
+            };
+            match address {
+                Some(address) if cfg.block_containing(address).is_none() => {
+                    writeln!(body, "    m.set({}, value);", address).unwrap()
+                }
+                _ => writeln!(
+                    body,
+                    "    if m.write({}, value) {{\n        return {};\n    }}",
+                    target(destination),
+                    next
+                )
+                .unwrap(),
+            }
+        }
+    }
+    writeln!(body, "    {}", tail).unwrap();
+    body
+}
+
+/// Transpiles a program into the source of a standalone Rust program.
+///
+/// The transpiled program reads its input values from stdin (separated by commas or whitespace),
+/// and prints its output values to stdout, one per line.
+/// Reachable code is compiled ahead of time into one function per basic block, dispatched through a jump table.
+/// Computed jumps to other addresses, and blocks that got overwritten, are run by an embedded interpreter instead.
+pub fn transpile(program: &[isize]) -> String {
+    let cfg = Cfg::new(program);
+    let blocks = &cfg.blocks;
+
+    let mut out = String::new();
+    writeln!(out, "//! Transpiled from an Intcode program.").unwrap();
+    writeln!(out).unwrap();
+    out.push_str(RUNTIME);
+    writeln!(out).unwrap();
+    writeln!(out, "const MEMORY_LIMIT: usize = {};", MEMORY_LIMIT).unwrap();
+    writeln!(out).unwrap();
+    let values: Vec<String> = program.iter().map(|value| value.to_string()).collect();
+    writeln!(out, "static PROGRAM: &[i64] = &[{}];", values.join(", ")).unwrap();
+
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "/// Returns the index of the block starting at an address."
+    )
+    .unwrap();
+    writeln!(out, "fn block_index(address: usize) -> Option<usize> {{").unwrap();
+    writeln!(out, "    match address {{").unwrap();
+    for (idx, block) in blocks.iter().enumerate() {
+        writeln!(out, "        {} => Some({}),", block.start, idx).unwrap();
+    }
+    writeln!(out, "        _ => None,").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "/// Returns the index of the block containing an address."
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "fn block_containing(address: usize) -> Option<usize> {{"
+    )
+    .unwrap();
+    writeln!(out, "    match address {{").unwrap();
+    for (idx, block) in blocks.iter().enumerate() {
+        writeln!(
+            out,
+            "        {}..={} => Some({}),",
+            block.start,
+            block.end - 1,
+            idx
+        )
+        .unwrap();
+    }
+    writeln!(out, "        _ => None,").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    for block in blocks.iter() {
+        let body = compile(block, &cfg);
+        let param = if body.contains("m.") { "m" } else { "_m" };
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "fn block_{:04}({}: &mut Machine) -> usize {{",
+            block.start, param
+        )
+        .unwrap();
+        out.push_str(&body);
+        writeln!(out, "}}").unwrap();
+    }
+
+    let mut dispatch = String::new();
+    for (idx, block) in blocks.iter().enumerate() {
+        writeln!(
+            dispatch,
+            "            {} if !m.dirty[{}] => block_{:04}(&mut m),",
+            block.start, idx, block.start
+        )
+        .unwrap();
+    }
+    writeln!(out).unwrap();
+    out.push_str(
+        &MAIN
+            .replace("{blocks}", &blocks.len().to_string())
+            .replace("{dispatch}", &dispatch),
+    );
+
+    out
+}
@@ -0,0 +1,54 @@
+//! Variable-length integer encoding, used by the binary formats of traces and snapshots.
+//! Signed integers are zigzag-encoded first, so that small negative values stay small.
+
+use std::io;
+use std::io::{Read, Write};
+
+pub fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buf[..len])
+}
+
+pub fn write_signed(writer: &mut impl Write, value: isize) -> io::Result<()> {
+    let value = value as i64;
+    write_varint(writer, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+/// Reads a varint, returns `None` if the reader was already at its end.
+pub fn read_varint(reader: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        if reader.read(&mut byte)? == 0 {
+            if shift == 0 {
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "varint too long",
+    ))
+}
+
+pub fn read_signed(reader: &mut impl Read) -> io::Result<isize> {
+    let value = read_varint(reader)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+    Ok(((value >> 1) as i64 ^ -((value & 1) as i64)) as isize)
+}
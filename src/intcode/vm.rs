@@ -0,0 +1,497 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use async_std::future;
+use async_std::sync;
+use futures::FutureExt;
+
+use super::budget::Limits;
+use super::{
+    Budget, Dense, Error, Event, Memory, Mode, Opcode, Sink, Snapshot, Source, Tracer, Word,
+};
+
+/// The state a machine is left in after executing an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum State<W = isize> {
+    /// The machine can keep going.
+    Running,
+    /// The machine is blocked on an input instruction, waiting for a value to be pushed.
+    NeedsInput,
+    /// The machine just produced a value.
+    Output(W),
+    /// The machine reached a halt instruction.
+    Halted,
+}
+
+/// How a machine run with `Vm::run_async` waits for its inputs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputPolicy<W = isize> {
+    /// Waits until a value is sent.
+    #[default]
+    Block,
+    /// Reads the given value when none was sent yet.
+    Default(W),
+    /// Reads the last value received again when none was sent yet, waits for the first one.
+    Replay,
+    /// Waits up to the given duration for a value to be sent, before giving up with `State::NeedsInput`.
+    Timeout(Duration),
+}
+
+/// A resumable Intcode machine, owning its memory and registers.
+/// The type of the values it computes with is the word type of its memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vm<M: Memory = Dense> {
+    memory: M,
+    ip: usize,
+    relative_base: isize,
+    inputs: VecDeque<M::Word>,
+    outputs: VecDeque<M::Word>,
+    /// The number of executed instructions.
+    steps: u64,
+    /// The number of executed instructions at which the budget must be checked again.
+    checkpoint: u64,
+    limits: Option<Limits>,
+    /// The number of memory cells allowed by the budget.
+    max_cells: usize,
+    /// The last value received by `run_async`, read again by `InputPolicy::Replay`.
+    last_input: Option<M::Word>,
+}
+
+impl Vm {
+    pub fn new(program: Vec<isize>) -> Vm {
+        Vm::with_memory(Dense::new(program))
+    }
+
+    /// Recreates a machine from a snapshot, using dense memory.
+    pub fn restore(snapshot: &Snapshot) -> Result<Vm, Error> {
+        Vm::restore_with(snapshot, Dense::default())
+    }
+}
+
+impl<M: Memory<Word = isize>> Vm<M> {
+    /// Recreates a machine from a snapshot, loading its memory into the given (empty) memory backend.
+    pub fn restore_with(snapshot: &Snapshot, memory: M) -> Result<Vm<M>, Error> {
+        let mut vm = Vm::with_memory(memory);
+        vm.ip = snapshot.ip;
+        for &(address, value) in snapshot.cells.iter() {
+            vm.write(address, value)?;
+        }
+        vm.relative_base = snapshot.relative_base;
+        vm.inputs = snapshot.inputs.iter().copied().collect();
+        vm.outputs = snapshot.outputs.iter().copied().collect();
+        Ok(vm)
+    }
+
+    /// Takes a copy of the full state of the machine, which can be serialized and restored later.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ip: self.ip,
+            relative_base: self.relative_base,
+            inputs: self.inputs.iter().copied().collect(),
+            outputs: self.outputs.iter().copied().collect(),
+            cells: self.memory.cells(),
+        }
+    }
+}
+
+impl<M: Memory> Vm<M> {
+    /// Creates a machine running on the given memory backend, already loaded with the program.
+    pub fn with_memory(memory: M) -> Vm<M> {
+        Vm {
+            memory,
+            ip: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+            steps: 0,
+            checkpoint: u64::MAX,
+            limits: None,
+            max_cells: usize::MAX,
+            last_input: None,
+        }
+    }
+
+    /// Limits the resources the machine can use from now on, replacing the previous budget.
+    /// Fuel and time are counted from this call, so a machine that ran out of them can be given more to resume.
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.limits = Some(Limits::new(budget, self.steps));
+        self.checkpoint = self.steps;
+        self.max_cells = budget.memory.unwrap_or(usize::MAX);
+    }
+
+    /// Returns the time left before the machine times out, if it has a deadline.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.limits?.remaining_time()
+    }
+
+    /// Creates an independent copy of the machine, to explore another branch of execution.
+    /// With `Sparse` memory, the copies share their memory pages until they write to them.
+    pub fn fork(&self) -> Vm<M>
+    where
+        M: Clone,
+    {
+        self.clone()
+    }
+
+    /// Returns the address of the next instruction to execute.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Returns the number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Returns the current relative base.
+    pub fn relative_base(&self) -> isize {
+        self.relative_base
+    }
+
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    pub fn into_memory(self) -> M {
+        self.memory
+    }
+
+    /// Queues a value to be consumed by the next input instructions.
+    pub fn push_input(&mut self, value: M::Word) {
+        self.inputs.push_back(value);
+    }
+
+    /// Returns the input values that haven't been consumed yet.
+    pub fn pending_inputs(&self) -> &VecDeque<M::Word> {
+        &self.inputs
+    }
+
+    /// Returns the oldest output value buffered by `run`.
+    pub fn pop_output(&mut self) -> Option<M::Word> {
+        self.outputs.pop_front()
+    }
+
+    /// Returns all the output values buffered by `run`, emptying the buffer.
+    pub fn take_outputs(&mut self) -> Vec<M::Word> {
+        self.outputs.drain(..).collect()
+    }
+
+    /// Returns the output values buffered by `run` that haven't been taken yet.
+    pub fn pending_outputs(&self) -> &VecDeque<M::Word> {
+        &self.outputs
+    }
+
+    /// Reads a memory cell, addresses that were never written to read as zero.
+    pub fn read(&self, address: usize) -> M::Word {
+        self.memory.read(address)
+    }
+
+    /// Returns the first address that can't be accessed, because of the memory or the budget.
+    #[inline]
+    fn limit(&self) -> usize {
+        self.memory.limit().min(self.max_cells)
+    }
+
+    /// Writes a memory cell, failing if the address is beyond the limit of the memory.
+    pub fn write(&mut self, address: usize, value: M::Word) -> Result<(), Error> {
+        let limit = self.limit();
+        if address >= limit {
+            return Err(Error::MemoryLimitExceeded {
+                address: self.ip,
+                target: address,
+                limit,
+            });
+        }
+        self.memory.write(address, value);
+        Ok(())
+    }
+
+    /// Converts a word into an integer, as needed by the current instruction to use it as an address, an opcode or an offset.
+    fn int(&self, word: &M::Word) -> Result<isize, Error> {
+        word.to_isize().ok_or(Error::Overflow { address: self.ip })
+    }
+
+    /// Converts a value into an address, as accessed by the current instruction.
+    fn check(&self, target: isize) -> Result<usize, Error> {
+        let address = usize::try_from(target).map_err(|_| Error::NegativeAddress {
+            address: self.ip,
+            target,
+        })?;
+        let limit = self.limit();
+        if address >= limit {
+            return Err(Error::MemoryLimitExceeded {
+                address: self.ip,
+                target: address,
+                limit,
+            });
+        }
+        Ok(address)
+    }
+
+    /// Returns the mode of the n-th parameter (starting at 1) of the current instruction.
+    fn mode(&self, n: usize) -> Result<Mode, Error> {
+        let instr = self.int(&self.read(self.ip))?;
+        let mode = (instr / 10isize.pow(n as u32 + 1)) % 10;
+        Mode::try_from(mode as usize).map_err(|_| Error::InvalidMode {
+            address: self.ip,
+            parameter: n,
+            mode,
+        })
+    }
+
+    /// Computes the address targeted by the n-th parameter of the current instruction.
+    fn address(&self, n: usize) -> Result<usize, Error> {
+        let arg = self.int(&self.read(self.ip + n))?;
+        match self.mode(n)? {
+            Mode::Position => self.check(arg),
+            Mode::Relative => self.check(
+                self.relative_base
+                    .checked_add(arg)
+                    .ok_or(Error::Overflow { address: self.ip })?,
+            ),
+            Mode::Immediate => Err(Error::ImmediateWrite {
+                address: self.ip,
+                parameter: n,
+            }),
+        }
+    }
+
+    /// Computes the value of the n-th parameter of the current instruction.
+    fn load(&self, n: usize) -> Result<M::Word, Error> {
+        match self.mode(n)? {
+            Mode::Immediate => Ok(self.read(self.ip + n)),
+            Mode::Position | Mode::Relative => Ok(self.read(self.address(n)?)),
+        }
+    }
+
+    /// Executes the instruction under the instruction pointer.
+    /// An input instruction without any queued value is left unexecuted, so it can be resumed once one is pushed.
+    pub fn step(&mut self) -> Result<State<M::Word>, Error> {
+        self.step_with(&mut ())
+    }
+
+    /// Executes the instruction under the instruction pointer, reporting it to the given tracer.
+    pub fn step_with<T: Tracer<M::Word> + ?Sized>(
+        &mut self,
+        tracer: &mut T,
+    ) -> Result<State<M::Word>, Error> {
+        let address = self.ip;
+        if self.steps >= self.checkpoint {
+            let limits = self
+                .limits
+                .expect("checkpoints are only set along with a budget");
+            self.checkpoint = limits.check(self.steps, address)?;
+        }
+        let instr = self.int(&self.read(address))?;
+        let opcode =
+            Opcode::try_from((instr % 100) as usize).map_err(|_| Error::UnknownOpcode {
+                address,
+                opcode: instr,
+            })?;
+        let mut event = Event {
+            address,
+            opcode,
+            operands: Default::default(),
+            written: None,
+            previous: M::Word::default(),
+        };
+        let mut next = address + 1 + opcode.arity();
+        let state = match opcode {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::EqualTo => {
+                let val1 = self.load(1)?;
+                let val2 = self.load(2)?;
+                let target = self.address(3)?;
+                let value = match opcode {
+                    Opcode::Add => val1.add(&val2).ok_or(Error::Overflow { address })?,
+                    Opcode::Multiply => val1.mul(&val2).ok_or(Error::Overflow { address })?,
+                    Opcode::LessThan => M::Word::from_isize((val1 < val2) as isize),
+                    Opcode::EqualTo => M::Word::from_isize((val1 == val2) as isize),
+                    _ => unreachable!(),
+                };
+                event.previous = self.read(target);
+                self.write(target, value.clone())?;
+                event.operands = [val1, val2, M::Word::from_isize(target as isize)];
+                event.written = Some((target, value));
+                State::Running
+            }
+            Opcode::Input => {
+                let target = self.address(1)?;
+                let value = match self.inputs.pop_front() {
+                    Some(value) => value,
+                    None => return Ok(State::NeedsInput),
+                };
+                event.previous = self.read(target);
+                self.write(target, value.clone())?;
+                event.operands[0] = M::Word::from_isize(target as isize);
+                event.written = Some((target, value));
+                State::Running
+            }
+            Opcode::Output => {
+                let value = self.load(1)?;
+                event.operands[0] = value.clone();
+                State::Output(value)
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let val1 = self.load(1)?;
+                let val2 = self.load(2)?;
+                if val1.is_zero() == (opcode == Opcode::JumpIfFalse) {
+                    next = self.check(self.int(&val2)?)?;
+                }
+                event.operands[0] = val1;
+                event.operands[1] = val2;
+                State::Running
+            }
+            Opcode::AdjustRelativeBase => {
+                let value = self.load(1)?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(self.int(&value)?)
+                    .ok_or(Error::Overflow { address })?;
+                event.operands[0] = value;
+                State::Running
+            }
+            Opcode::Halt => {
+                next = address;
+                State::Halted
+            }
+        };
+        self.ip = next;
+        self.steps += 1;
+        tracer.trace(&event);
+        Ok(state)
+    }
+
+    /// Executes instructions until the machine needs an input, produces an output or halts.
+    pub fn run_until_io(&mut self) -> Result<State<M::Word>, Error> {
+        self.run_until_io_with(&mut ())
+    }
+
+    /// Reverts the effects of an executed instruction, as reported to a tracer, consumed inputs get queued back.
+    /// Instructions must be reverted in the reverse order of their execution.
+    /// Output values can't be taken back, so they are left as is.
+    pub fn undo(&mut self, event: &Event<M::Word>) {
+        if let Some((address, value)) = &event.written {
+            self.memory.write(*address, event.previous.clone());
+            if event.opcode == Opcode::Input {
+                self.inputs.push_front(value.clone());
+            }
+        }
+        if event.opcode == Opcode::AdjustRelativeBase {
+            let offset = event.operands[0].to_isize();
+            self.relative_base -= offset.expect("the relative base got adjusted by this offset");
+        }
+        self.ip = event.address;
+    }
+
+    /// Executes instructions until the machine needs an input or halts, buffering the values it outputs.
+    /// The returned state is either `State::NeedsInput` or `State::Halted`.
+    pub fn run(&mut self) -> Result<State<M::Word>, Error> {
+        loop {
+            match self.run_until_io()? {
+                State::Output(value) => self.outputs.push_back(value),
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Executes instructions until the machine halts, pulling inputs from a source and pushing outputs to a sink.
+    /// Returns `State::NeedsInput` when the source has no value to give, so that the machine can be resumed later.
+    pub fn run_io<I, O>(&mut self, input: &mut I, output: &mut O) -> Result<State<M::Word>, Error>
+    where
+        I: Source<M::Word> + ?Sized,
+        O: Sink<M::Word> + ?Sized,
+    {
+        loop {
+            match self.run_until_io()? {
+                State::NeedsInput => match input.read() {
+                    Some(value) => self.push_input(value),
+                    None => return Ok(State::NeedsInput),
+                },
+                State::Output(value) => output.write(value),
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Executes instructions until the machine halts, communicating through channels.
+    /// Inputs are waited for as the policy says, the value read again by `InputPolicy::Replay` being kept across runs.
+    /// Returns `State::NeedsInput` when the policy gave up waiting for an input, so that the machine can be resumed later.
+    /// Fails with `Error::InputStarved` once every sender is gone, whatever the policy.
+    pub async fn run_async(
+        &mut self,
+        input: &sync::Receiver<M::Word>,
+        output: &sync::Sender<M::Word>,
+        policy: &InputPolicy<M::Word>,
+    ) -> Result<State<M::Word>, Error> {
+        loop {
+            match self.run_until_io()? {
+                State::NeedsInput => match self.receive(input, policy).await? {
+                    Some(value) => {
+                        self.last_input = Some(value.clone());
+                        self.push_input(value);
+                    }
+                    None => return Ok(State::NeedsInput),
+                },
+                State::Output(value) => output.send(value).await,
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Waits for an input value as the policy says, returning `None` if the policy gave up.
+    async fn receive(
+        &self,
+        input: &sync::Receiver<M::Word>,
+        policy: &InputPolicy<M::Word>,
+    ) -> Result<Option<M::Word>, Error> {
+        let address = self.ip;
+        let fallback = match policy {
+            InputPolicy::Default(value) => Some(value.clone()),
+            InputPolicy::Replay => self.last_input.clone(),
+            InputPolicy::Block | InputPolicy::Timeout(_) => None,
+        };
+        if let Some(fallback) = fallback {
+            return match input.recv().now_or_never() {
+                Some(Some(value)) => Ok(Some(value)),
+                // the channel is closed, so no value will ever be sent again.
+                Some(None) => Err(Error::InputStarved { address }),
+                None => Ok(Some(fallback)),
+            };
+        }
+
+        let patience = match policy {
+            InputPolicy::Timeout(patience) => Some(*patience),
+            _ => None,
+        };
+        let deadline = self.remaining_time();
+        let value = match patience.into_iter().chain(deadline).min() {
+            Some(wait) => match future::timeout(wait, input.recv()).await {
+                Ok(value) => value,
+                Err(_) => match (patience, deadline) {
+                    (Some(patience), Some(deadline)) if deadline <= patience => {
+                        return Err(Error::TimedOut { address })
+                    }
+                    (Some(_), _) => return Ok(None),
+                    (None, _) => return Err(Error::TimedOut { address }),
+                },
+            },
+            None => input.recv().await,
+        };
+        value.map(Some).ok_or(Error::InputStarved { address })
+    }
+
+    /// Executes instructions until the machine needs an input, produces an output or halts,
+    /// reporting each of them to the given tracer.
+    pub fn run_until_io_with<T: Tracer<M::Word> + ?Sized>(
+        &mut self,
+        tracer: &mut T,
+    ) -> Result<State<M::Word>, Error> {
+        loop {
+            match self.step_with(tracer)? {
+                State::Running => {}
+                state => return Ok(state),
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+/// The type of the values held by the memory cells of a machine.
+///
+/// Addresses, opcodes and offsets must still fit in an `isize`, only the arithmetic of programs depends on the word type.
+pub trait Word: Clone + Default + Ord + fmt::Debug + fmt::Display {
+    fn from_isize(value: isize) -> Self;
+
+    /// Converts the word into an `isize`, if it fits.
+    fn to_isize(&self) -> Option<isize>;
+
+    /// Returns the sum of two words, or `None` if it can't be represented.
+    fn add(&self, rhs: &Self) -> Option<Self>;
+
+    /// Returns the product of two words, or `None` if it can't be represented.
+    fn mul(&self, rhs: &Self) -> Option<Self>;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Primitive integers wrap around on overflow, whatever the build profile.
+macro_rules! wrapping {
+    ($($ty:ty),*) => {$(
+        impl Word for $ty {
+            #[inline]
+            fn from_isize(value: isize) -> $ty {
+                value as $ty
+            }
+
+            #[inline]
+            fn to_isize(&self) -> Option<isize> {
+                isize::try_from(*self).ok()
+            }
+
+            #[inline]
+            fn add(&self, rhs: &$ty) -> Option<$ty> {
+                Some(self.wrapping_add(*rhs))
+            }
+
+            #[inline]
+            fn mul(&self, rhs: &$ty) -> Option<$ty> {
+                Some(self.wrapping_mul(*rhs))
+            }
+
+            #[inline]
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+        }
+    )*};
+}
+
+wrapping!(isize, i64, i128);
+
+/// A primitive integer word making machines fail with `Error::Overflow` instead of wrapping around.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checked<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for Checked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+macro_rules! checked {
+    ($($ty:ty),*) => {$(
+        impl Word for Checked<$ty> {
+            #[inline]
+            fn from_isize(value: isize) -> Checked<$ty> {
+                Checked(value as $ty)
+            }
+
+            #[inline]
+            fn to_isize(&self) -> Option<isize> {
+                isize::try_from(self.0).ok()
+            }
+
+            #[inline]
+            fn add(&self, rhs: &Checked<$ty>) -> Option<Checked<$ty>> {
+                self.0.checked_add(rhs.0).map(Checked)
+            }
+
+            #[inline]
+            fn mul(&self, rhs: &Checked<$ty>) -> Option<Checked<$ty>> {
+                self.0.checked_mul(rhs.0).map(Checked)
+            }
+
+            #[inline]
+            fn is_zero(&self) -> bool {
+                self.0 == 0
+            }
+        }
+    )*};
+}
+
+checked!(isize, i64, i128);
+
+/// Arbitrary-precision integers never overflow.
+impl Word for BigInt {
+    fn from_isize(value: isize) -> BigInt {
+        BigInt::from(value)
+    }
+
+    fn to_isize(&self) -> Option<isize> {
+        ToPrimitive::to_isize(self)
+    }
+
+    fn add(&self, rhs: &BigInt) -> Option<BigInt> {
+        Some(self + rhs)
+    }
+
+    fn mul(&self, rhs: &BigInt) -> Option<BigInt> {
+        Some(self * rhs)
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+}
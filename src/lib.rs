@@ -1 +1,3 @@
 pub type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+pub mod intcode;
@@ -0,0 +1,113 @@
+//! Assembles small sources, checking the programs they make and the errors they fail with.
+
+use aoc_2019::intcode::{self, AsmError};
+
+#[test]
+fn relative_operands() {
+    let spaced = intcode::assemble("add rb + 3, rb - 2, rb\nhlt").expect("invalid source");
+    let packed = intcode::assemble("add rb+3, rb-2, rb+0\nhlt").expect("invalid source");
+    assert_eq!(spaced, [22201, 3, -2, 0, 99]);
+    assert_eq!(spaced, packed);
+}
+
+#[test]
+fn labels_and_constants() {
+    let source = "
+            const STEP = 2
+            add [counter], #STEP, [counter]
+            jt #1, #end - 1
+            db 7
+        end:
+            hlt
+        counter:
+            db 5
+    ";
+    assert_eq!(
+        intcode::assemble(source).expect("invalid source"),
+        [1001, 9, 2, 9, 1105, 1, 7, 7, 99, 5]
+    );
+}
+
+#[test]
+fn out_of_range_values() {
+    let source = format!("const BIG = {}\n\nout #BIG + BIG", isize::MAX);
+    assert_eq!(
+        intcode::assemble(&source),
+        Err(AsmError {
+            line: 3,
+            message: "value out of range".to_string(),
+        })
+    );
+    let source = format!("out #0 - {} - 2", isize::MAX);
+    assert_eq!(
+        intcode::assemble(&source).map_err(|error| error.line),
+        Err(1)
+    );
+}
+
+#[test]
+fn operands_without_mode() {
+    let error = intcode::assemble("hlt\nout 5").expect_err("the operand has no mode");
+    assert_eq!(error.line, 2);
+}
+
+#[test]
+fn macros_with_parameters() {
+    let source = "
+            macro mov from, to
+                add from, #0, to
+            endm
+            mov #5, [x]
+            mov [x], [y]
+            hlt
+        x:
+            db 0
+        y:
+            db 0
+    ";
+    assert_eq!(
+        intcode::assemble(source).expect("invalid source"),
+        [1101, 5, 0, 9, 1001, 9, 0, 10, 99, 0, 0]
+    );
+}
+
+#[test]
+fn nested_macros() {
+    // every expansion of `skip` gets its own label.
+    let source = "
+            macro skip cond
+                jt cond, #over\\@
+                out #1
+            over\\@:
+            endm
+            macro twice cond
+                skip cond
+                skip cond
+            endm
+            twice #0
+            hlt
+    ";
+    assert_eq!(
+        intcode::assemble(source).expect("invalid source"),
+        [1105, 0, 5, 104, 1, 1105, 0, 10, 104, 1, 99]
+    );
+}
+
+#[test]
+fn macro_errors() {
+    assert_eq!(
+        intcode::assemble("hlt\nmacro forever\n    hlt\n"),
+        Err(AsmError {
+            line: 2,
+            message: "unterminated macro".to_string(),
+        })
+    );
+    // lines expanded from a macro are reported at its outermost invocation.
+    assert_eq!(
+        intcode::assemble("macro again\n    again\nendm\nagain"),
+        Err(AsmError {
+            line: 4,
+            message: "macro expansion too deep".to_string(),
+        })
+    );
+}
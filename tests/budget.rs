@@ -0,0 +1,82 @@
+//! Runs machines on a budget, checking where they stop once they run out of it.
+
+use std::time::Duration;
+
+use aoc_2019::intcode::{self, Budget, Error, State, Vm};
+
+/// Jumps back to itself forever.
+const LOOP: &str = "1105,1,0";
+
+/// Adds two numbers, then halts: two instructions.
+const ADD: &str = "1101,1,1,5,99,0";
+
+fn vm(program: &str, budget: Budget) -> Vm {
+    let mut vm = Vm::new(intcode::parse(program));
+    vm.set_budget(budget);
+    vm
+}
+
+#[test]
+fn fuel() {
+    let budget = |fuel| Budget {
+        fuel: Some(fuel),
+        ..Budget::default()
+    };
+    assert_eq!(vm(ADD, budget(2)).run(), Ok(State::Halted));
+    assert_eq!(
+        vm(ADD, budget(1)).run(),
+        Err(Error::OutOfFuel { address: 4 })
+    );
+
+    let mut vm = vm(LOOP, budget(10));
+    assert_eq!(vm.run(), Err(Error::OutOfFuel { address: 0 }));
+    assert_eq!(vm.steps(), 10);
+
+    // fuel is counted from the new budget, so the machine can resume.
+    vm.set_budget(budget(5));
+    assert_eq!(vm.run(), Err(Error::OutOfFuel { address: 0 }));
+    assert_eq!(vm.steps(), 15);
+}
+
+#[test]
+fn memory() {
+    let budget = Budget {
+        memory: Some(100),
+        ..Budget::default()
+    };
+    assert_eq!(vm("1101,1,1,99,99", budget).run(), Ok(State::Halted));
+    assert_eq!(
+        vm("1101,1,1,100,99", budget).run(),
+        Err(Error::MemoryLimitExceeded {
+            address: 0,
+            target: 100,
+            limit: 100,
+        })
+    );
+    assert_eq!(
+        vm("4,100,99", budget).run(),
+        Err(Error::MemoryLimitExceeded {
+            address: 0,
+            target: 100,
+            limit: 100,
+        })
+    );
+}
+
+#[test]
+fn timeout() {
+    let budget = |timeout| Budget {
+        timeout: Some(timeout),
+        ..Budget::default()
+    };
+    let mut vm = vm(LOOP, budget(Duration::from_secs(0)));
+    assert_eq!(vm.run(), Err(Error::TimedOut { address: 0 }));
+    assert_eq!(vm.steps(), 0);
+
+    // the clock is only read every 1024 instructions.
+    vm.set_budget(budget(Duration::from_millis(20)));
+    assert_eq!(vm.run(), Err(Error::TimedOut { address: 0 }));
+    assert!(vm.steps() > 0);
+    assert_eq!(vm.steps() % 1024, 0);
+    assert_eq!(vm.remaining_time(), Some(Duration::from_secs(0)));
+}
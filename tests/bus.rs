@@ -0,0 +1,91 @@
+//! Runs machines on a packet bus, checking how packets are routed and when the bus reports idle rounds.
+
+use aoc_2019::intcode::{self, Bus, Packet, Traffic, MONITOR_ADDRESS};
+
+/// Forwards every packet `(x, y)` it receives to the monitor as `(x + address, y)`,
+/// counting in its last cell the times it found no packet waiting.
+/// The machine at address 0 first sends `(5, 6)` to the machine at address 1.
+const FORWARD: &str = "
+        in [addr]
+        jt [addr], #loop
+        out #1
+        out #5
+        out #6
+    loop:
+        in [x]
+        eq [x], #-1, [empty]
+        jt [empty], #idle
+        in [y]
+        add [x], [addr], [x]
+        out #255
+        out [x]
+        out [y]
+        jt #1, #loop
+    idle:
+        add [polls], #1, [polls]
+        jt #1, #loop
+    addr:
+        db 0
+    x:
+        db 0
+    y:
+        db 0
+    empty:
+        db 0
+    polls:
+        db 0
+";
+
+fn packet(address: isize, x: isize, y: isize) -> Packet {
+    Packet { address, x, y }
+}
+
+#[test]
+fn routing() {
+    let program = intcode::assemble(FORWARD).expect("invalid source");
+    let mut bus = Bus::boot(&program, 3);
+    assert_eq!(
+        bus.poll(),
+        Ok(Traffic::Monitor(packet(MONITOR_ADDRESS, 6, 6)))
+    );
+
+    assert_eq!(bus.send(packet(2, 1, 2)), Ok(()));
+    assert_eq!(bus.send(packet(3, 1, 2)), Err(packet(3, 1, 2)));
+    assert_eq!(bus.send(packet(-1, 1, 2)), Err(packet(-1, 1, 2)));
+    assert_eq!(
+        bus.poll(),
+        Ok(Traffic::Monitor(packet(MONITOR_ADDRESS, 3, 2)))
+    );
+}
+
+#[test]
+fn idle() {
+    let program = intcode::assemble(FORWARD).expect("invalid source");
+    let polls = program.len() - 1;
+    let mut bus = Bus::boot(&program, 3);
+    assert!(matches!(bus.poll(), Ok(Traffic::Monitor(_))));
+
+    // the round where packets were exchanged doesn't count, the next one reads -1 everywhere.
+    assert_eq!(bus.poll(), Ok(Traffic::Idle));
+    let counts: Vec<_> = (0..3).map(|node| bus.node(node).read(polls)).collect();
+    assert_eq!(counts, [1, 1, 1]);
+
+    assert_eq!(bus.poll(), Ok(Traffic::Idle));
+    bus.send(packet(0, 0, 0)).expect("node 0 is on the bus");
+    assert!(matches!(bus.poll(), Ok(Traffic::Monitor(_))));
+    let counts: Vec<_> = (0..3).map(|node| bus.node(node).read(polls)).collect();
+    assert_eq!(counts, [2, 2, 2]);
+}
+
+#[test]
+fn monitor_and_undelivered() {
+    let program = intcode::parse("104,9,104,1,104,2,99");
+    let mut bus = Bus::boot(&program, 1);
+    assert_eq!(bus.poll(), Ok(Traffic::Undelivered(packet(9, 1, 2))));
+    assert_eq!(bus.poll(), Ok(Traffic::Halted));
+
+    let mut bus = Bus::boot(&program, 1);
+    bus.set_monitor(9);
+    assert_eq!(bus.poll(), Ok(Traffic::Monitor(packet(9, 1, 2))));
+    assert_eq!(bus.poll(), Ok(Traffic::Halted));
+}
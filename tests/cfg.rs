@@ -0,0 +1,60 @@
+//! Builds control-flow graphs of small programs, checking their blocks and edges.
+
+use aoc_2019::intcode::{self, Cfg, Edge, EdgeKind};
+
+/// Outputs its input down to 1, then jumps through a pointer unless its counter is back to zero.
+const SOURCE: &str = "
+        in [n]
+    loop:
+        jf [n], #done
+        out [n]
+        add [n], #-1, [n]
+        jt #1, #loop
+    done:
+        jf [n], [ptr]
+        hlt
+    n:
+        db 0
+    ptr:
+        db 0
+";
+
+fn edge(from: usize, to: usize, kind: EdgeKind) -> Edge {
+    Edge { from, to, kind }
+}
+
+#[test]
+fn blocks_and_edges() {
+    let cfg = Cfg::new(&intcode::assemble(SOURCE).expect("invalid source"));
+    let blocks: Vec<_> = cfg
+        .blocks
+        .iter()
+        .map(|block| (block.start, block.end))
+        .collect();
+    assert_eq!(blocks, [(0, 2), (2, 5), (5, 14), (14, 17), (17, 18)]);
+    assert_eq!(
+        cfg.edges,
+        [
+            edge(0, 2, EdgeKind::Fallthrough),
+            edge(2, 14, EdgeKind::Jump),
+            edge(2, 5, EdgeKind::Fallthrough),
+            edge(5, 2, EdgeKind::Jump),
+            edge(14, 17, EdgeKind::Fallthrough),
+        ]
+    );
+    assert_eq!(cfg.successors(5).count(), 1);
+    assert_eq!(cfg.indirect_jumps(), [14]);
+    assert!(cfg.blocks[4].halts());
+    assert_eq!(cfg.block_containing(9).map(|block| block.start), Some(5));
+    assert_eq!(cfg.block_containing(18), None);
+}
+
+#[test]
+fn dot() {
+    let cfg = Cfg::new(&intcode::assemble(SOURCE).expect("invalid source"));
+    let dot = cfg.to_dot();
+    assert!(dot.starts_with("digraph intcode {"));
+    assert!(dot.contains("L0005 -> L0002 [style=solid];"));
+    assert!(dot.contains("L0002 -> L0005 [style=dashed];"));
+    assert!(dot.contains("L0014 -> indirect [style=dotted];"));
+}
@@ -0,0 +1,99 @@
+//! Runs the corpus on every machine of the crate, checking their outputs, how they end and their final memory.
+//! The transpiler is checked against the same corpus along with its other tests.
+
+mod corpus;
+
+use aoc_2019::intcode::{
+    self, Backend, BigInt, Case, Checked, Engine, Interpreter, Symbolic, SymbolicError, Word,
+};
+
+use corpus::{Example, CORPUS};
+
+/// Plenty for any program of the corpus, which all end on their own.
+const FUEL: u64 = 100_000;
+
+#[test]
+fn engines() {
+    for example in CORPUS {
+        let case = Case {
+            program: intcode::parse(example.program),
+            inputs: example.inputs.to_vec(),
+        };
+        for engine in Engine::ALL.iter() {
+            let outcome = engine.run(&case, FUEL);
+            let context = format!("{} on {}", example.name, engine.name());
+            assert_eq!(outcome.outputs, example.outputs, "{}", context);
+            assert_eq!(outcome.end, example.end(), "{}", context);
+
+            let memory: Vec<_> = (0..example.memory.len())
+                .map(|address| {
+                    let cell = outcome.memory.iter().find(|&&(idx, _)| idx == address);
+                    cell.map_or(0, |&(_, value)| value)
+                })
+                .collect();
+            assert_eq!(memory, example.memory, "{}", context);
+        }
+    }
+}
+
+/// Runs an example with the interpreter, computing with the given word type.
+fn interpret<W: Word>(example: &Example, word: &str) {
+    let interpreter = Interpreter::new(intcode::parse(example.program));
+    let inputs = example.inputs.iter().map(|&value| W::from_isize(value));
+    let mut outputs = Vec::new();
+    let end = interpreter.run_with(inputs, |value: W| outputs.push(value.to_isize()));
+
+    let context = format!("{} with {} words", example.name, word);
+    let outputs: Option<Vec<_>> = outputs.into_iter().collect();
+    assert_eq!(outputs.as_deref(), Some(example.outputs), "{}", context);
+    match end {
+        Ok(memory) => {
+            assert_eq!(example.fault, None, "{}", context);
+            let memory: Option<Vec<_>> = memory.iter().map(Word::to_isize).collect();
+            let memory = memory.expect("the memory doesn't fit in an isize");
+            assert!(memory.starts_with(example.memory), "{}", context);
+        }
+        Err(error) => assert_eq!(Some(error), example.fault, "{}", context),
+    }
+}
+
+#[test]
+fn words() {
+    for example in CORPUS {
+        interpret::<isize>(example, "isize");
+        interpret::<i128>(example, "i128");
+        interpret::<Checked<i64>>(example, "checked i64");
+        interpret::<BigInt>(example, "big integer");
+    }
+}
+
+/// Symbolic machines make up their own inputs, so only the examples without any can be run concretely.
+#[test]
+fn symbolic() {
+    for example in CORPUS.iter().filter(|example| example.inputs.is_empty()) {
+        let mut machine = Symbolic::new(&intcode::parse(example.program));
+        let end = machine.run();
+
+        let outputs: Vec<_> = machine
+            .outputs()
+            .iter()
+            .map(|expr| expr.as_const())
+            .collect();
+        let expected: Vec<_> = example.outputs.iter().copied().map(Some).collect();
+        assert_eq!(outputs, expected, "{}", example.name);
+        assert_eq!(
+            end,
+            example
+                .fault
+                .map_or(Ok(()), |error| Err(SymbolicError::Fault(error))),
+            "{}",
+            example.name
+        );
+
+        let memory: Vec<_> = (0..example.memory.len())
+            .map(|address| machine.read(address).as_const())
+            .collect();
+        let expected: Vec<_> = example.memory.iter().copied().map(Some).collect();
+        assert_eq!(memory, expected, "{}", example.name);
+    }
+}
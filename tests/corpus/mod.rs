@@ -0,0 +1,390 @@
+//! Small programs covering every opcode and parameter mode, along with what they must do.
+//! The examples of the puzzles come first, then edge cases they don't exercise.
+
+// shared by several test crates, which don't all look at every field.
+#![allow(dead_code)]
+
+use aoc_2019::intcode::{Error, State};
+
+pub struct Example {
+    pub name: &'static str,
+    /// The program, in the comma-separated format of the puzzle inputs.
+    pub program: &'static str,
+    pub inputs: &'static [isize],
+    pub outputs: &'static [isize],
+    /// The first cells of memory once the program ended.
+    pub memory: &'static [isize],
+    /// The error the program dies with, after producing its outputs, if it doesn't halt.
+    pub fault: Option<Error>,
+}
+
+impl Example {
+    /// Returns how a run of the program must end.
+    pub fn end(&self) -> Result<State, Error> {
+        match self.fault {
+            Some(error) => Err(error),
+            None => Ok(State::Halted),
+        }
+    }
+}
+
+const EXAMPLE: Example = Example {
+    name: "",
+    program: "",
+    inputs: &[],
+    outputs: &[],
+    memory: &[],
+    fault: None,
+};
+
+const QUINE: &[isize] = &[
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+const COMPARE_TO_8: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,\
+                            1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,\
+                            1105,1,46,98,99";
+
+pub const CORPUS: &[Example] = &[
+    // day 2
+    Example {
+        name: "day 2, example 1",
+        program: "1,9,10,3,2,3,11,0,99,30,40,50",
+        memory: &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 2, example 2",
+        program: "1,0,0,0,99",
+        memory: &[2, 0, 0, 0, 99],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 2, example 3",
+        program: "2,3,0,3,99",
+        memory: &[2, 3, 0, 6, 99],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 2, example 4",
+        program: "2,4,4,5,99,0",
+        memory: &[2, 4, 4, 5, 99, 9801],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 2, example 5",
+        program: "1,1,1,4,99,5,6,0,99",
+        memory: &[30, 1, 1, 4, 2, 5, 6, 0, 99],
+        ..EXAMPLE
+    },
+    // day 5
+    Example {
+        name: "day 5, echo",
+        program: "3,0,4,0,99",
+        inputs: &[42],
+        outputs: &[42],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, immediate multiplication",
+        program: "1002,4,3,4,33",
+        memory: &[1002, 4, 3, 4, 99],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, negative values",
+        program: "1101,100,-1,4,0",
+        memory: &[1101, 100, -1, 4, 99],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, equal to 8 in position mode",
+        program: "3,9,8,9,10,9,4,9,99,-1,8",
+        inputs: &[8],
+        outputs: &[1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, not equal to 8 in position mode",
+        program: "3,9,8,9,10,9,4,9,99,-1,8",
+        inputs: &[5],
+        outputs: &[0],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, less than 8 in position mode",
+        program: "3,9,7,9,10,9,4,9,99,-1,8",
+        inputs: &[5],
+        outputs: &[1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, not less than 8 in position mode",
+        program: "3,9,7,9,10,9,4,9,99,-1,8",
+        inputs: &[8],
+        outputs: &[0],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, equal to 8 in immediate mode",
+        program: "3,3,1108,-1,8,3,4,3,99",
+        inputs: &[8],
+        outputs: &[1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, not equal to 8 in immediate mode",
+        program: "3,3,1108,-1,8,3,4,3,99",
+        inputs: &[9],
+        outputs: &[0],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, less than 8 in immediate mode",
+        program: "3,3,1107,-1,8,3,4,3,99",
+        inputs: &[-7],
+        outputs: &[1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, not less than 8 in immediate mode",
+        program: "3,3,1107,-1,8,3,4,3,99",
+        inputs: &[8],
+        outputs: &[0],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, zero jump in position mode",
+        program: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+        inputs: &[0],
+        outputs: &[0],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, non-zero jump in position mode",
+        program: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+        inputs: &[3],
+        outputs: &[1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, zero jump in immediate mode",
+        program: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+        inputs: &[0],
+        outputs: &[0],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, non-zero jump in immediate mode",
+        program: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+        inputs: &[-2],
+        outputs: &[1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, below 8",
+        program: COMPARE_TO_8,
+        inputs: &[7],
+        outputs: &[999],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, equal to 8",
+        program: COMPARE_TO_8,
+        inputs: &[8],
+        outputs: &[1000],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 5, above 8",
+        program: COMPARE_TO_8,
+        inputs: &[9],
+        outputs: &[1001],
+        ..EXAMPLE
+    },
+    // day 9
+    Example {
+        name: "day 9, quine",
+        program: "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+        outputs: QUINE,
+        memory: QUINE,
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 9, 16-digit product",
+        program: "1102,34915192,34915192,7,4,7,99,0",
+        outputs: &[1_219_070_632_396_864],
+        ..EXAMPLE
+    },
+    Example {
+        name: "day 9, large number",
+        program: "104,1125899906842624,99",
+        outputs: &[1_125_899_906_842_624],
+        ..EXAMPLE
+    },
+    // relative mode
+    Example {
+        name: "relative read",
+        program: "109,4,204,1,99,77",
+        outputs: &[77],
+        ..EXAMPLE
+    },
+    Example {
+        name: "relative input",
+        program: "109,7,203,0,204,0,99",
+        inputs: &[13],
+        outputs: &[13],
+        memory: &[109, 7, 203, 0, 204, 0, 99, 13],
+        ..EXAMPLE
+    },
+    Example {
+        name: "relative addition write",
+        program: "109,12,21101,3,4,-2,204,-2,99",
+        outputs: &[7],
+        memory: &[109, 12, 21101, 3, 4, -2, 204, -2, 99, 0, 7],
+        ..EXAMPLE
+    },
+    Example {
+        name: "relative comparison writes",
+        program: "109,20,21107,1,2,0,21208,0,1,1,204,0,204,1,99",
+        outputs: &[1, 1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "relative jump target",
+        program: "109,8,2105,1,0,104,1,99,7",
+        ..EXAMPLE
+    },
+    Example {
+        name: "relative base adjusted by a relative parameter",
+        program: "209,5,204,-1,99,3",
+        outputs: &[204],
+        ..EXAMPLE
+    },
+    Example {
+        name: "negative relative base",
+        program: "109,-5,204,10,99,42",
+        outputs: &[42],
+        ..EXAMPLE
+    },
+    Example {
+        name: "relative base lowered",
+        program: "109,10,109,-4,204,-1,99",
+        outputs: &[-1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "relative write with a negative base",
+        program: "109,-3,21101,6,7,13,204,13,99",
+        outputs: &[13],
+        memory: &[109, -3, 21101, 6, 7, 13, 204, 13, 99, 0, 13],
+        ..EXAMPLE
+    },
+    // self-modification
+    Example {
+        name: "instruction replaced by a halt",
+        program: "1101,99,0,4,104,1,99",
+        memory: &[1101, 99, 0, 4, 99, 1, 99],
+        ..EXAMPLE
+    },
+    Example {
+        name: "operand patched",
+        program: "1101,0,42,5,104,0,99",
+        outputs: &[42],
+        ..EXAMPLE
+    },
+    Example {
+        name: "addition turned into a multiplication",
+        program: "1101,1101,1,4,1101,3,4,9,104,0,99",
+        outputs: &[12],
+        memory: &[1101, 1101, 1, 4, 1102, 3, 4, 9, 104, 12, 99],
+        ..EXAMPLE
+    },
+    Example {
+        name: "jump to an input instruction written by the program",
+        program: "1101,3,0,9,1105,1,9,99,99,0,14,4,14,99,0",
+        inputs: &[-8],
+        outputs: &[-8],
+        ..EXAMPLE
+    },
+    // miscellaneous
+    Example {
+        name: "negative product",
+        program: "1102,-3,7,7,4,7,99,0",
+        outputs: &[-21],
+        ..EXAMPLE
+    },
+    Example {
+        name: "untaken jumps",
+        program: "1105,0,7,1106,1,7,104,1,99",
+        outputs: &[1],
+        ..EXAMPLE
+    },
+    Example {
+        name: "taken jump if false",
+        program: "1106,0,5,104,1,104,2,99",
+        outputs: &[2],
+        ..EXAMPLE
+    },
+    Example {
+        name: "write beyond the program",
+        program: "1101,1,2,1000,4,1000,99",
+        outputs: &[3],
+        ..EXAMPLE
+    },
+    Example {
+        name: "read beyond the program",
+        program: "4,500,99",
+        outputs: &[0],
+        ..EXAMPLE
+    },
+    // faults
+    Example {
+        name: "unknown opcode",
+        program: "104,5,98",
+        outputs: &[5],
+        fault: Some(Error::UnknownOpcode {
+            address: 2,
+            opcode: 98,
+        }),
+        ..EXAMPLE
+    },
+    Example {
+        name: "invalid mode",
+        program: "304,0,99",
+        fault: Some(Error::InvalidMode {
+            address: 0,
+            parameter: 1,
+            mode: 3,
+        }),
+        ..EXAMPLE
+    },
+    Example {
+        name: "immediate write",
+        program: "11101,1,1,5,99",
+        fault: Some(Error::ImmediateWrite {
+            address: 0,
+            parameter: 3,
+        }),
+        ..EXAMPLE
+    },
+    Example {
+        name: "negative relative address",
+        program: "109,-1,204,0,99",
+        fault: Some(Error::NegativeAddress {
+            address: 2,
+            target: -1,
+        }),
+        ..EXAMPLE
+    },
+    Example {
+        name: "memory limit",
+        program: "1101,1,1,16777216,99",
+        fault: Some(Error::MemoryLimitExceeded {
+            address: 0,
+            target: 1 << 24,
+            limit: 1 << 24,
+        }),
+        ..EXAMPLE
+    },
+];
@@ -0,0 +1,57 @@
+//! Drives the debugger forwards and backwards, checking where it stops.
+
+use aoc_2019::intcode::{self, Debugger, Stop, Vm};
+
+/// Counts down from 3 in the cell at address 11, then halts.
+const COUNTDOWN: &str = "1001,11,-1,11,1005,11,0,99,0,0,0,3";
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    let mut dbg = Debugger::new(Vm::new(intcode::parse(COUNTDOWN)));
+    dbg.set_breakpoint(7);
+    dbg.set_watchpoint(11);
+    assert_eq!(
+        dbg.cont(),
+        Ok(Stop::Watchpoint {
+            address: 11,
+            old: 3,
+            new: 2,
+        })
+    );
+    dbg.remove_watchpoint(11);
+    assert_eq!(dbg.cont(), Ok(Stop::Breakpoint(7)));
+    assert_eq!(dbg.cont(), Ok(Stop::Halted));
+}
+
+#[test]
+fn reverse() {
+    let mut dbg = Debugger::new(Vm::new(intcode::parse(COUNTDOWN)));
+    dbg.set_breakpoint(7);
+    assert_eq!(dbg.cont(), Ok(Stop::Breakpoint(7)));
+    assert_eq!(dbg.vm().read(11), 0);
+
+    dbg.set_watchpoint(11);
+    assert_eq!(
+        dbg.reverse_cont(),
+        Stop::Watchpoint {
+            address: 11,
+            old: 0,
+            new: 1,
+        }
+    );
+    assert_eq!(dbg.vm().ip(), 0);
+    assert_eq!(dbg.last_write(11).map(|event| event.address), Some(0));
+}
+
+#[test]
+fn edits_forget_the_history() {
+    let mut dbg = Debugger::new(Vm::new(intcode::parse(COUNTDOWN)));
+    dbg.step().expect("invalid program");
+    dbg.set_watchpoint(1000);
+    dbg.write(1000, 5).expect("invalid address");
+    assert!(dbg.history().is_empty());
+    assert_eq!(dbg.reverse_cont(), Stop::HistoryStart);
+    assert_eq!(dbg.vm().read(1000), 5);
+    assert_eq!(dbg.last_write(1000), None);
+    assert_eq!(dbg.step(), Ok(Stop::Stepped));
+}
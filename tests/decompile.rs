@@ -0,0 +1,83 @@
+//! Decompiles small programs, checking the pseudo-code recovered from them.
+
+use aoc_2019::intcode;
+
+fn decompile(source: &str) -> String {
+    intcode::decompile(&intcode::assemble(source).expect("invalid source"))
+}
+
+#[test]
+fn call_returning_into_data() {
+    // stores a return address like a call would, but it points to data rather than code.
+    let output = decompile(
+        "
+            arb #100
+            add #back, #0, rb+0
+            jt #1, #func
+        back:
+            db 0
+        func:
+            out #7
+            hlt
+        ",
+    );
+    assert_eq!(
+        output,
+        "fn main() {\n    rb += 100;\n    ret = 9;\n    output(7);\n    halt();\n}\n"
+    );
+}
+
+#[test]
+fn conflicting_stack_offsets() {
+    // once both branches join with different relative bases, the slots can't be named anymore.
+    let output = decompile(
+        "
+            in [c]
+            jt [c], #a
+            arb #1
+            jt #1, #x
+        a:
+            arb #2
+        x:
+            out rb+0
+            jt #1, #y
+        y:
+            out rb+1
+            hlt
+        c:
+            db 0
+        ",
+    );
+    assert!(
+        output.contains("    output(rb[0]);\n    output(rb[1]);\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn puzzle_inputs() {
+    for input in [
+        include_str!("../inputs/day5.txt"),
+        include_str!("../inputs/day9.txt"),
+        include_str!("../inputs/day13.txt"),
+    ]
+    .iter()
+    {
+        let output = intcode::decompile(&intcode::parse(input));
+        assert!(output.starts_with("fn main() {"), "{}", output);
+    }
+}
+
+#[test]
+fn extreme_values() {
+    let decompile = |program| intcode::decompile(&intcode::parse(program));
+    assert!(decompile("1101,1,-9223372036854775808,0,99")
+        .contains("mem[0] = 1 + -9223372036854775808;"));
+    assert!(decompile("21101,1,1,-9223372036854775808,99")
+        .contains("rb[-9223372036854775808] = 1 + 1;"));
+    assert!(
+        decompile("109,9223372036854775807,109,9223372036854775807,99")
+            .contains("rb += 9223372036854775807;\n    halt();")
+    );
+}
@@ -0,0 +1,88 @@
+//! Connects machines to every kind of device, checking the values they read and write.
+
+use std::collections::VecDeque;
+use std::sync::mpsc;
+
+use aoc_2019::intcode::{self, Ascii, Iter, Pipe, Sink, Source, State, Vm};
+
+/// Outputs every input it reads, forever.
+const ECHO: &str = "3,7,4,7,1105,1,0,0";
+
+fn echo<I: Source, O: Sink>(input: &mut I, output: &mut O) {
+    let mut vm = Vm::new(intcode::parse(ECHO));
+    assert_eq!(vm.run_io(input, output), Ok(State::NeedsInput));
+}
+
+#[test]
+fn queues() {
+    let mut input: VecDeque<_> = vec![1, 2, 3].into();
+    let mut output = Vec::new();
+    echo(&mut input, &mut output);
+    assert!(input.is_empty());
+    assert_eq!(output, [1, 2, 3]);
+
+    let mut output = VecDeque::new();
+    echo(&mut Iter(4..7), &mut output);
+    assert_eq!(output, [4, 5, 6]);
+
+    let mut last = None;
+    echo(&mut Iter(4..7), &mut last);
+    assert_eq!(last, Some(6));
+}
+
+#[test]
+fn closures() {
+    let mut count = 0;
+    let mut input = || {
+        count += 1;
+        Some(count).filter(|&count| count <= 3)
+    };
+    let mut output = Vec::new();
+    echo(&mut input, &mut |value| output.push(value * 10));
+    assert_eq!(output, [10, 20, 30]);
+}
+
+#[test]
+fn channels() {
+    let (sender, mut input) = mpsc::channel();
+    let (mut output, receiver) = mpsc::channel();
+    for value in 1..=3 {
+        sender.send(value).expect("the receiver is alive");
+    }
+    drop(sender);
+    echo(&mut input, &mut output);
+    drop(output);
+    assert_eq!(receiver.iter().collect::<Vec<_>>(), [1, 2, 3]);
+}
+
+#[test]
+fn pipes() {
+    let pipe = Pipe::new();
+    let mut first = Vm::new(intcode::parse(ECHO));
+    let mut second = Vm::new(intcode::parse(ECHO));
+    let mut output = Vec::new();
+
+    assert_eq!(
+        first.run_io(&mut Iter(1..=2), &mut pipe.clone()),
+        Ok(State::NeedsInput)
+    );
+    assert_eq!(pipe.len(), 2);
+    assert_eq!(
+        second.run_io(&mut pipe.clone(), &mut output),
+        Ok(State::NeedsInput)
+    );
+    assert!(pipe.is_empty());
+    assert_eq!(output, [1, 2]);
+}
+
+#[test]
+fn ascii() {
+    let mut input = Ascii::new("hi");
+    input.push_str("!\n");
+    let mut output = String::new();
+    echo(&mut input, &mut output);
+    assert_eq!(output, "hi!\n");
+
+    echo(&mut Iter(vec![72, 1000, 128].into_iter()), &mut output);
+    assert_eq!(output, "hi!\nH1000\n128\n");
+}
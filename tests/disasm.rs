@@ -0,0 +1,55 @@
+//! Disassembles programs and assembles the listings back, checking that nothing gets lost on the way.
+
+use aoc_2019::intcode::{self, Item, Mode, Opcode};
+
+/// Checks that the listing of a program assembles back into the same program.
+fn round_trip(name: &str, program: &[isize]) {
+    let listing = intcode::disassemble(program).to_string();
+    let assembled = intcode::assemble(&listing).expect("the listing doesn't assemble");
+    assert_eq!(assembled, program, "{}:\n{}", name, listing);
+}
+
+#[test]
+fn puzzle_inputs() {
+    let inputs = [
+        ("day2", include_str!("../inputs/day2.txt")),
+        ("day5", include_str!("../inputs/day5.txt")),
+        ("day7", include_str!("../inputs/day7.txt")),
+        ("day9", include_str!("../inputs/day9.txt")),
+        ("day11", include_str!("../inputs/day11.txt")),
+        ("day13", include_str!("../inputs/day13.txt")),
+    ];
+    for (name, input) in inputs.iter() {
+        round_trip(name, &intcode::parse(input));
+    }
+}
+
+#[test]
+fn data_and_unknown_opcodes() {
+    // cells the code jumps over or never reaches, or that don't decode as instructions, are kept as data.
+    round_trip("data", &intcode::parse("1105,1,4,-7,99,98,1234,-5,0"));
+    round_trip("invalid modes", &intcode::parse("1106,0,3,30001,5,6,7"));
+}
+
+#[test]
+fn code_and_data() {
+    let listing = intcode::disassemble(&intcode::parse("1105,1,4,17,104,3,99"));
+    let items: Vec<_> = listing
+        .lines
+        .iter()
+        .map(|line| (line.address, &line.item))
+        .collect();
+    assert!(
+        matches!(items[0], (0, Item::Instruction(ref jump)) if jump.opcode == Opcode::JumpIfTrue)
+    );
+    assert_eq!(items[1], (3, &Item::Data(vec![17])));
+    match items[2] {
+        (4, Item::Instruction(output)) => {
+            assert_eq!(output.opcode, Opcode::Output);
+            assert_eq!(output.operands[0].mode, Mode::Immediate);
+        }
+        item => panic!("expected an output instruction, got {:?}", item),
+    }
+    assert!(listing.labels.contains(&4));
+    assert!(listing.to_string().contains("jt #1, #L0004"));
+}
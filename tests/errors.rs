@@ -0,0 +1,95 @@
+//! Checks that faulty programs die with the error describing what went wrong, and where.
+
+use aoc_2019::intcode::{self, Error, Interpreter};
+
+/// Runs a program with the given inputs, returning the error it died with.
+fn fault(program: &str, inputs: &[isize]) -> Error {
+    Interpreter::new(intcode::parse(program))
+        .run(inputs.iter().copied(), |_| ())
+        .expect_err("the program halted")
+}
+
+#[test]
+fn unknown_opcode() {
+    assert_eq!(
+        fault("104,1,98", &[]),
+        Error::UnknownOpcode {
+            address: 2,
+            opcode: 98,
+        }
+    );
+    // the opcode is only known once the first instruction wrote it.
+    assert_eq!(
+        fault("1101,97,1,4,0", &[]),
+        Error::UnknownOpcode {
+            address: 4,
+            opcode: 98,
+        }
+    );
+}
+
+#[test]
+fn invalid_mode() {
+    assert_eq!(
+        fault("1,0,0,0,304,0,99", &[]),
+        Error::InvalidMode {
+            address: 4,
+            parameter: 1,
+            mode: 3,
+        }
+    );
+}
+
+#[test]
+fn negative_address() {
+    assert_eq!(
+        fault("4,-3,99", &[]),
+        Error::NegativeAddress {
+            address: 0,
+            target: -3,
+        }
+    );
+    assert_eq!(
+        fault("1106,0,-1", &[]),
+        Error::NegativeAddress {
+            address: 0,
+            target: -1,
+        }
+    );
+}
+
+#[test]
+fn immediate_write() {
+    assert_eq!(
+        fault("3,0,103,7,99", &[1]),
+        Error::ImmediateWrite {
+            address: 2,
+            parameter: 1,
+        }
+    );
+}
+
+#[test]
+fn input_starved() {
+    assert_eq!(
+        fault("3,9,3,9,99", &[1]),
+        Error::InputStarved { address: 2 }
+    );
+}
+
+#[test]
+fn messages() {
+    let error = Error::InvalidMode {
+        address: 4,
+        parameter: 2,
+        mode: 5,
+    };
+    assert_eq!(
+        error.to_string(),
+        "invalid mode 5 for parameter 2 of the instruction at address 4"
+    );
+    assert_eq!(
+        Error::InputStarved { address: 2 }.to_string(),
+        "no more input available for the instruction at address 2"
+    );
+}
@@ -0,0 +1,49 @@
+//! Runs self-modifying programs on `FastVm`, checking that it recompiles the instructions they overwrite.
+
+use aoc_2019::intcode::{self, Error, FastVm, State, Vm, MEMORY_LIMIT};
+
+/// Writes `out #5` and a jump back at the very end of memory, runs it, then patches it into `out #6` and runs it again.
+const FAR: &str = "
+        add #104, #0, [16777211]
+        add #5, #0, [16777212]
+        add #1105, #0, [16777213]
+        add #1, #0, [16777214]
+        add #back, #0, [16777215]
+        jt #1, #16777211
+    back:
+        jt [done], #end
+        add #1, #0, [done]
+        add #6, #0, [16777212]
+        jt #1, #16777211
+    end:
+        hlt
+    done:
+        db 0
+";
+
+#[test]
+fn recompiles_far_code() {
+    let program = intcode::assemble(FAR).expect("invalid source");
+    let mut vm = Vm::new(program.clone());
+    assert_eq!(vm.run(), Ok(State::Halted));
+    assert_eq!(vm.take_outputs(), [5, 6]);
+
+    let mut fast = FastVm::new(program);
+    assert_eq!(fast.run(), Ok(State::Halted));
+    assert_eq!(fast.take_outputs(), [5, 6]);
+    assert_eq!(fast.memory().len(), MEMORY_LIMIT);
+}
+
+#[test]
+fn memory_limit() {
+    let mut fast = FastVm::new(intcode::parse("99"));
+    assert_eq!(fast.write(MEMORY_LIMIT - 1, 1), Ok(()));
+    assert_eq!(
+        fast.write(MEMORY_LIMIT, 1),
+        Err(Error::MemoryLimitExceeded {
+            address: 0,
+            target: MEMORY_LIMIT,
+            limit: MEMORY_LIMIT,
+        })
+    );
+}
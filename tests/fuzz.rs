@@ -0,0 +1,74 @@
+//! Runs randomly generated programs on every machine, and checks that they all behave like the reference one.
+//! The same cases can be explored with `cargo fuzz`, see the `fuzz` directory.
+
+use aoc_2019::intcode::{self, Backend, Case, Engine, Item, Outcome, FUZZ_FUEL};
+
+/// The number of cases generated by each test.
+const CASES: u64 = 2_000;
+
+/// Checks a case on the given backends, panicking with a shrunk reproducer if one of them diverges.
+fn check(seed: u64, backends: &[&dyn Backend]) {
+    let case = Case::random(seed);
+    if let Err(divergence) = case.check(backends, FUZZ_FUEL) {
+        let reproducer = case.shrink(|case| case.check(backends, FUZZ_FUEL).is_err());
+        panic!("seed {}: {}\n{}", seed, divergence, reproducer);
+    }
+}
+
+#[test]
+fn differential() {
+    for seed in 0..CASES {
+        check(seed, &[&Engine::Sparse, &Engine::Fast]);
+    }
+}
+
+#[test]
+fn generated_programs_are_well_formed() {
+    for seed in 0..CASES {
+        let case = Case::random(seed);
+        let listing = intcode::disassemble(&case.program);
+        assert!(
+            matches!(listing.lines[0].item, Item::Instruction(_)),
+            "seed {}: the program doesn't start with an instruction",
+            seed
+        );
+    }
+}
+
+/// A backend doubling the outputs of the reference machine that are equal to 7.
+struct Buggy;
+
+impl Backend for Buggy {
+    fn name(&self) -> String {
+        "buggy".to_string()
+    }
+
+    fn run(&self, case: &Case, fuel: u64) -> Outcome {
+        let mut outcome = Engine::Vm.run(case, fuel);
+        for value in outcome.outputs.iter_mut().filter(|value| **value == 7) {
+            *value = 14;
+        }
+        outcome
+    }
+}
+
+#[test]
+fn shrinks_to_assembler_reproducers() {
+    let backends: [&dyn Backend; 1] = [&Buggy];
+    let case = (0..CASES)
+        .map(Case::random)
+        .find(|case| case.check(&backends, FUZZ_FUEL).is_err())
+        .expect("no generated program outputs 7");
+
+    let reproducer = case.shrink(|case| case.check(&backends, FUZZ_FUEL).is_err());
+    assert!(reproducer.check(&backends, FUZZ_FUEL).is_err());
+    assert!(
+        reproducer.program.len() < case.program.len(),
+        "not shrunk:\n{}",
+        reproducer
+    );
+
+    let source = reproducer.to_string();
+    let program = intcode::assemble(&source).expect("the reproducer doesn't assemble");
+    assert_eq!(program, reproducer.program, "in:\n{}", source);
+}
@@ -0,0 +1,77 @@
+//! Wires machines together, checking the values they pass around and how the network stops.
+
+use aoc_2019::intcode::{self, Error, Network, NetworkError, Vm};
+
+/// Outputs its input plus one, then halts.
+const INCREMENT: &str = "3,9,1001,9,1,9,4,9,99,0";
+
+/// Outputs every input plus one, forever.
+const FOREVER: &str = "3,11,1001,11,1,11,4,11,1105,1,0,0";
+
+fn vms(program: &str, count: usize) -> Vec<Vm> {
+    (0..count)
+        .map(|_| Vm::new(intcode::parse(program)))
+        .collect()
+}
+
+#[test]
+fn chain() {
+    let mut network = Network::chain(vms(INCREMENT, 3));
+    network.push_input(0, 10).collect(2);
+    assert_eq!(network.run(), Ok(()));
+    assert_eq!(network.outputs(2), [13]);
+    assert!((0..3).all(|node| network.is_halted(node)));
+}
+
+#[test]
+fn broadcast() {
+    let mut network = Network::new();
+    for vm in vms(INCREMENT, 3) {
+        network.add(vm);
+    }
+    network.broadcast(0, &[1, 2]).push_input(0, 0);
+    network.collect(1).collect(2);
+    assert_eq!(network.run(), Ok(()));
+    assert_eq!(network.outputs(1), [2]);
+    assert_eq!(network.outputs(2), [2]);
+}
+
+#[test]
+fn deadlock() {
+    // nobody sends the first value around the ring.
+    let mut network = Network::ring(vms(FOREVER, 2));
+    assert_eq!(
+        network.run(),
+        Err(NetworkError::Deadlock {
+            blocked: vec![0, 1]
+        })
+    );
+
+    // the value goes around once, then the last node halts and keeps the next one in its queue.
+    let mut network = Network::ring(vms(FOREVER, 2).into_iter().chain(vms(INCREMENT, 1)));
+    network.push_input(0, 0).collect(2);
+    assert_eq!(
+        network.run(),
+        Err(NetworkError::Deadlock {
+            blocked: vec![0, 1]
+        })
+    );
+    assert_eq!(network.outputs(2), [3]);
+    assert!(network.is_halted(2));
+}
+
+#[test]
+fn fault() {
+    let mut network = Network::chain(vms(INCREMENT, 1).into_iter().chain(vms("4,-1,99", 1)));
+    network.push_input(0, 0);
+    assert_eq!(
+        network.run(),
+        Err(NetworkError::Fault {
+            node: 1,
+            error: Error::NegativeAddress {
+                address: 0,
+                target: -1
+            }
+        })
+    );
+}
@@ -0,0 +1,94 @@
+//! Schedules machines on the current thread, checking the order they run in and how the run ends.
+
+use std::cell::RefCell;
+
+use aoc_2019::intcode::{self, NetworkError, Pipe, Scheduler, Sink, State, Vm};
+
+/// Outputs every input plus one, halting once it output 5 or more.
+const PING: &str = "
+    loop:
+        in [x]
+        add [x], #1, [x]
+        out [x]
+        lt [x], #5, [more]
+        jt [more], #loop
+        hlt
+    x:
+        db 0
+    more:
+        db 0
+";
+
+fn ping() -> Vm {
+    Vm::new(intcode::assemble(PING).expect("invalid source"))
+}
+
+#[test]
+fn all_halted() {
+    let outputs = RefCell::new(Vec::new());
+    let mut scheduler = Scheduler::new();
+    for value in 1..=3 {
+        let program = intcode::parse(&format!("104,{},99", value));
+        scheduler.spawn(
+            Vm::new(program),
+            || None,
+            |value| outputs.borrow_mut().push(value),
+        );
+    }
+    assert_eq!(scheduler.run(), Ok(()));
+    assert!((0..3).all(|task| *scheduler.state(task) == State::Halted));
+    assert_eq!(scheduler.round(), Ok(false));
+    drop(scheduler);
+    assert_eq!(outputs.into_inner(), [1, 2, 3]);
+}
+
+#[test]
+fn ordering() {
+    let log = RefCell::new(Vec::new());
+    let (a, b) = (Pipe::new(), Pipe::new());
+    a.clone().write(0);
+
+    let mut scheduler = Scheduler::new();
+    for (task, (input, output)) in [(&a, &b), (&b, &a)].iter().enumerate() {
+        let (log, mut output) = (&log, Pipe::clone(output));
+        scheduler.spawn(ping(), Pipe::clone(input), move |value| {
+            log.borrow_mut().push((task, value));
+            output.write(value);
+        });
+    }
+    assert_eq!(scheduler.run(), Ok(()));
+    drop(scheduler);
+    assert_eq!(
+        log.into_inner(),
+        [(0, 1), (1, 2), (0, 3), (1, 4), (0, 5), (1, 6)]
+    );
+    // the last value is left for a machine that already halted.
+    assert_eq!(a.len(), 1);
+}
+
+#[test]
+fn deadlock() {
+    let (a, b) = (Pipe::new(), Pipe::new());
+    let mut scheduler = Scheduler::new();
+    scheduler.spawn(ping(), a.clone(), b.clone());
+    scheduler.spawn(ping(), b, a);
+    assert_eq!(
+        scheduler.run(),
+        Err(NetworkError::Deadlock {
+            blocked: vec![0, 1]
+        })
+    );
+    assert_eq!(*scheduler.state(0), State::NeedsInput);
+
+    // once the first machine halts, nothing can feed the second one anymore.
+    let pipe = Pipe::new();
+    let mut scheduler = Scheduler::new();
+    scheduler.spawn(Vm::new(intcode::parse("104,1,99")), || None, pipe.clone());
+    scheduler.spawn(ping(), pipe, |_| {});
+    assert_eq!(
+        scheduler.run(),
+        Err(NetworkError::Deadlock { blocked: vec![1] })
+    );
+    // the cell `x` of the second machine holds the one value it got.
+    assert_eq!(scheduler.vm(1).read(16), 2);
+}
@@ -0,0 +1,67 @@
+//! Freezes machines mid-run, checking that they resume from their snapshots just like the originals.
+
+use std::io;
+
+use aoc_2019::intcode::{self, Error, Snapshot, Sparse, State, Vm, MEMORY_LIMIT};
+
+#[test]
+fn resume() {
+    let program = intcode::parse(include_str!("../inputs/day9.txt"));
+    let mut vm = Vm::new(program);
+    vm.push_input(1);
+    for _ in 0..100 {
+        vm.step().expect("invalid program");
+    }
+
+    let bytes = vm.snapshot().to_bytes();
+    let snapshot = Snapshot::from_bytes(&bytes).expect("invalid snapshot");
+    assert_eq!(snapshot, vm.snapshot());
+    let mut restored = Vm::restore(&snapshot).expect("invalid snapshot");
+    let mut fork = vm.fork();
+
+    for vm in [&mut vm, &mut restored, &mut fork].iter_mut() {
+        assert_eq!(vm.run(), Ok(State::Halted));
+    }
+    let outputs = vm.take_outputs();
+    assert_eq!(restored.take_outputs(), outputs);
+    assert_eq!(fork.take_outputs(), outputs);
+}
+
+#[test]
+fn malformed() {
+    let error = Snapshot::from_bytes(b"ICTR\x02").expect_err("read a trace as a snapshot");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    let bytes = Snapshot::default().to_bytes();
+    let error =
+        Snapshot::from_bytes(&bytes[..bytes.len() - 1]).expect_err("read a truncated snapshot");
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+    let snapshot = Snapshot {
+        cells: vec![(usize::MAX, 1)],
+        ..Snapshot::default()
+    };
+    let error = Snapshot::from_bytes(&snapshot.to_bytes())
+        .expect_err("read cells beyond the address space");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn beyond_dense_memory() {
+    let mut vm = Vm::with_memory(Sparse::new(intcode::parse("1101,7,0,100000000,99")));
+    assert_eq!(vm.run(), Ok(State::Halted));
+    let snapshot = Snapshot::from_bytes(&vm.snapshot().to_bytes()).expect("invalid snapshot");
+    assert_eq!(snapshot, vm.snapshot());
+
+    let restored =
+        Vm::restore_with(&snapshot, Sparse::default()).expect("cells beyond sparse memory");
+    assert_eq!(restored.read(100_000_000), 7);
+    assert_eq!(
+        Vm::restore(&snapshot),
+        Err(Error::MemoryLimitExceeded {
+            address: 4,
+            target: 100_000_000,
+            limit: MEMORY_LIMIT,
+        })
+    );
+}
@@ -0,0 +1,77 @@
+//! Runs programs symbolically, checking that solving for unknown cells agrees with trying every value.
+
+use std::iter;
+use std::ops::RangeInclusive;
+
+use aoc_2019::intcode::{self, Error, Interpreter, Symbolic, SymbolicError};
+use itertools::Itertools;
+
+/// Finds the first values of the unknown cells for which the program leaves `target` at `address`, by brute force.
+fn enumerate(
+    program: &[isize],
+    unknowns: &[(usize, RangeInclusive<isize>)],
+    address: usize,
+    target: isize,
+) -> Option<Vec<isize>> {
+    let values = unknowns.iter().map(|(_, range)| range.clone());
+    values.multi_cartesian_product().find(|values| {
+        let mut program = program.to_vec();
+        for (&(cell, _), &value) in unknowns.iter().zip(values) {
+            program[cell] = value;
+        }
+        let memory = Interpreter::new(program).run(iter::empty(), |_| {});
+        memory.is_ok_and(|memory| memory[address] == target)
+    })
+}
+
+#[test]
+fn day2() {
+    let program = intcode::parse(include_str!("../inputs/day2.txt"));
+    let unknowns = [(1, 0..=99), (2, 0..=99)];
+    for &target in [19_690_720, 4_462_686, 1].iter() {
+        assert_eq!(
+            intcode::search(&program, &unknowns, 0, target),
+            enumerate(&program, &unknowns, 0, target),
+            "{}",
+            target
+        );
+    }
+}
+
+#[test]
+fn branches() {
+    // doubles the unknown cell, unless it is below 5.
+    let program = intcode::parse("1007,20,5,21,1005,21,12,1002,20,2,20,99,99,0,0,0,0,0,0,0,0,0");
+    let unknowns = [(20, 0..=9)];
+    for target in 0..20 {
+        assert_eq!(
+            intcode::search(&program, &unknowns, 20, target),
+            enumerate(&program, &unknowns, 20, target),
+            "{}",
+            target
+        );
+    }
+}
+
+#[test]
+fn relative_base_overflow() {
+    let mut machine = Symbolic::new(&intcode::parse(&format!("109,{0},109,{0},99", isize::MAX)));
+    assert_eq!(
+        machine.run(),
+        Err(SymbolicError::Fault(Error::Overflow { address: 2 }))
+    );
+}
+
+#[test]
+fn division_overflow() {
+    // the cell at address 0 ends up as -mem5, which can't be isize::MIN.
+    let program = intcode::parse("102,-1,5,0,99,0");
+    assert_eq!(
+        intcode::search(&program, &[(5, 0..=0)], 0, isize::MIN),
+        None
+    );
+    assert_eq!(
+        intcode::search(&program, &[(5, -3..=3)], 0, 2),
+        Some(vec![-2])
+    );
+}
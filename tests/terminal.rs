@@ -0,0 +1,70 @@
+//! Talks to machines through terminals, checking the lines they exchange.
+
+use std::io;
+
+use aoc_2019::intcode::{self, Encoding, Message, State, Terminal, Vm};
+
+/// Prompts for a character with `ok?`, then echoes it along with a value out of the ASCII range.
+const PROMPT: &str = "
+        out #111
+        out #107
+        out #63
+        in [c]
+        out [c]
+        out #10
+        out #1000
+        out [c]
+        hlt
+    c:
+        db 0
+";
+
+fn terminal(encoding: Encoding) -> Terminal {
+    let program = intcode::assemble(PROMPT).expect("invalid source");
+    Terminal::new(Vm::new(program), encoding)
+}
+
+fn text(line: &str) -> Message {
+    Message::Text(line.to_string())
+}
+
+#[test]
+fn ascii() {
+    let mut terminal = terminal(Encoding::Ascii);
+    assert_eq!(terminal.run(), Ok((State::NeedsInput, vec![text("ok?")])));
+    terminal.send("A\n").expect("invalid line");
+    assert_eq!(
+        terminal.run(),
+        Ok((
+            State::Halted,
+            vec![text("A"), Message::Value(1000), text("A")]
+        ))
+    );
+}
+
+#[test]
+fn numeric() {
+    let mut terminal = terminal(Encoding::Numeric);
+    assert!(terminal.send("twelve").is_err());
+    let (state, lines) = terminal.run().expect("invalid program");
+    assert_eq!(state, State::NeedsInput);
+    assert_eq!(lines.len(), 3);
+    terminal.send(" 12 ").expect("invalid line");
+    let (state, lines) = terminal.run().expect("invalid program");
+    assert_eq!(state, State::Halted);
+    assert_eq!(lines[..2], [Message::Value(12), Message::Value(10)]);
+}
+
+#[test]
+fn interact() {
+    let mut output = Vec::new();
+    terminal(Encoding::Ascii)
+        .interact(io::Cursor::new("B\n"), &mut output)
+        .expect("invalid program");
+    assert_eq!(String::from_utf8(output).unwrap(), "ok?\nB\n1000\nB\n");
+
+    let mut output = Vec::new();
+    let result = terminal(Encoding::Ascii).interact(io::empty(), &mut output);
+    assert!(result.is_err());
+    assert_eq!(String::from_utf8(output).unwrap(), "ok?\n");
+}
@@ -0,0 +1,77 @@
+//! Records traces of programs, checking that they read back as the events the machine reported.
+
+use std::io;
+
+use aoc_2019::intcode::{
+    self, Event, Opcode, Profiler, State, TraceReader, TraceWriter, Tracer, Vm,
+};
+
+/// Keeps every event it is given.
+#[derive(Default)]
+struct Events(Vec<Event>);
+
+impl Tracer for Events {
+    fn trace(&mut self, event: &Event) {
+        self.0.push(*event);
+    }
+}
+
+/// Runs a program until it halts, returning the events it went through along with their trace.
+fn record(program: &str, inputs: &[isize]) -> (Vec<Event>, Vec<u8>) {
+    let mut vm = Vm::new(intcode::parse(program));
+    for &input in inputs {
+        vm.push_input(input);
+    }
+    let mut events = Events::default();
+    let mut writer = TraceWriter::new(Vec::new()).expect("can't write to a vector");
+    let mut tracer = (&mut events, &mut writer);
+    while vm.step_with(&mut tracer).expect("invalid program") != State::Halted {}
+    (events.0, writer.finish().expect("can't write to a vector"))
+}
+
+#[test]
+fn round_trip() {
+    let (events, trace) = record("109,-2,21101,3,4,16,3,0,204,16,1106,0,13,99,0", &[-8]);
+    let read: io::Result<Vec<_>> = TraceReader::new(&trace[..])
+        .expect("invalid trace")
+        .collect();
+    assert_eq!(read.expect("invalid trace"), events);
+
+    let add = events[1];
+    assert_eq!(add.address, 2);
+    assert_eq!(add.opcode, Opcode::Add);
+    assert_eq!(add.operands, [3, 4, 14]);
+    assert_eq!(add.written, Some((14, 7)));
+    assert_eq!(add.previous, 0);
+    let input = events[2];
+    assert_eq!(input.written, Some((0, -8)));
+    assert_eq!(input.previous, 109);
+}
+
+#[test]
+fn invalid_traces() {
+    let error = TraceReader::new(&b"trace"[..]).err().expect("read a trace");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    let (_, trace) = record("1101,1,2,7,104,5,99,0", &[]);
+    let truncated = &trace[..trace.len() - 1];
+    let read: io::Result<Vec<_>> = TraceReader::new(truncated)
+        .expect("invalid trace")
+        .collect();
+    assert_eq!(
+        read.expect_err("read a truncated trace").kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn profile() {
+    // counts down from 3, looping over the same four instructions.
+    let mut vm = Vm::new(intcode::parse("1001,11,-1,11,1005,11,0,99,0,0,0,3"));
+    let mut profiler = Profiler::new();
+    while vm.step_with(&mut profiler).expect("invalid program") != State::Halted {}
+    assert_eq!(profiler.total(), 7);
+    assert_eq!(profiler.hottest(2), [(0, 3), (4, 3)]);
+    assert_eq!(profiler.opcodes()[&Opcode::Add], 3);
+    assert_eq!(profiler.opcodes()[&Opcode::Halt], 1);
+}
@@ -0,0 +1,186 @@
+//! Compiles transpiled programs with `rustc`, and checks that they behave just like the interpreter.
+//! They are built without optimizations, so that arithmetic overflows panic where the interpreter would wrap around.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+mod corpus;
+
+use aoc_2019::intcode::{self, Backend, Case, Engine, Error, Interpreter, FUZZ_FUEL};
+
+use corpus::CORPUS;
+
+/// Transpiles a program and compiles it, returning the path of the executable.
+fn compile(name: &str, program: &[isize]) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("transpile");
+    fs::create_dir_all(&dir).expect("can't create the output directory");
+    let source = dir.join(format!("{}.rs", name));
+    fs::write(&source, intcode::transpile(program)).expect("can't write the transpiled program");
+
+    let binary = dir.join(name);
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc)
+        .args(["--edition", "2018", "-D", "warnings", "-o"])
+        .arg(&binary)
+        .arg(&source)
+        .output()
+        .expect("can't run rustc");
+    assert!(
+        output.status.success(),
+        "the transpiled program for {} doesn't compile:\n{}",
+        name,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    binary
+}
+
+/// Runs a transpiled program, returning its outputs, or `None` if it failed.
+fn run(binary: &Path, inputs: &[isize]) -> Option<Vec<isize>> {
+    let mut child = Command::new(binary)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("can't run the transpiled program");
+    let inputs: Vec<String> = inputs.iter().map(|value| value.to_string()).collect();
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    writeln!(stdin, "{}", inputs.join("\n")).expect("can't write the inputs");
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .expect("can't wait for the transpiled program");
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).expect("outputs aren't UTF-8");
+    let outputs = stdout
+        .lines()
+        .map(|line| line.parse().expect("invalid output"));
+    Some(outputs.collect())
+}
+
+/// Checks that the transpiled program produces the same outputs as the interpreter, or fails as well.
+fn check(binary: &Path, program: &[isize], inputs: &[isize]) {
+    let mut expected = Vec::new();
+    let result = Interpreter::new(program.to_vec())
+        .run(inputs.iter().copied(), |value| expected.push(value))
+        .ok()
+        .map(|_| expected);
+    assert_eq!(run(binary, inputs), result, "with inputs {:?}", inputs);
+}
+
+#[test]
+fn day5() {
+    let program = intcode::parse(include_str!("../inputs/day5.txt"));
+    let binary = compile("day5", &program);
+    check(&binary, &program, &[1]);
+    check(&binary, &program, &[5]);
+    check(&binary, &program, &[]);
+}
+
+#[test]
+fn day7() {
+    let program = intcode::parse(include_str!("../inputs/day7.txt"));
+    let binary = compile("day7", &program);
+    for phase in 0..5 {
+        check(&binary, &program, &[phase, 0]);
+        check(&binary, &program, &[phase, 1234]);
+    }
+}
+
+#[test]
+fn day9() {
+    let program = intcode::parse(include_str!("../inputs/day9.txt"));
+    let binary = compile("day9", &program);
+    check(&binary, &program, &[1]);
+    check(&binary, &program, &[2]);
+}
+
+#[test]
+fn day13() {
+    let program = intcode::parse(include_str!("../inputs/day13.txt"));
+    let binary = compile("day13", &program);
+    check(&binary, &program, &[]);
+}
+
+#[test]
+fn self_modifying() {
+    // counts down from its input, patching its own output instruction and looping through a computed jump.
+    let source = "
+            in [counter]
+        loop:
+            out [counter]
+            add [counter], #-1, [counter]
+            mul [counter], #10, [patch+1]
+        patch:
+            out #0
+            jf [counter], #done
+            add #loop, #0, [pointer]
+            jt #1, [pointer]
+        done:
+            hlt
+        counter:
+            db 0
+        pointer:
+            db 0
+    ";
+    let program = intcode::assemble(source).expect("invalid test program");
+    let binary = compile("self_modifying", &program);
+    check(&binary, &program, &[5]);
+    check(&binary, &program, &[1]);
+    check(&binary, &program, &[]);
+}
+
+#[test]
+fn overflow() {
+    // the product wraps around, then adjusting the relative base by it overflows.
+    let program = intcode::parse(
+        "1102,4611686018427387904,4,13,4,13,9,13,109,9223372036854775807,109,1,99,0",
+    );
+    let binary = compile("overflow", &program);
+    check(&binary, &program, &[]);
+    let program = intcode::parse("21101,9223372036854775807,1,0,4,0,99");
+    let binary = compile("relative_overflow", &program);
+    check(&binary, &program, &[]);
+}
+
+#[test]
+fn faulty() {
+    // jumps to a negative address if its input is 10, halts otherwise.
+    let program = intcode::parse("3,11,1001,11,-10,11,1006,11,-1,99,0,0");
+    let binary = compile("faulty", &program);
+    check(&binary, &program, &[10]);
+    check(&binary, &program, &[3]);
+}
+
+#[test]
+fn conformance() {
+    for (idx, example) in CORPUS.iter().enumerate() {
+        let binary = compile(&format!("corpus{}", idx), &intcode::parse(example.program));
+        let expected = match example.fault {
+            Some(_) => None,
+            None => Some(example.outputs.to_vec()),
+        };
+        assert_eq!(run(&binary, example.inputs), expected, "{}", example.name);
+    }
+}
+
+#[test]
+fn generated() {
+    // only programs that end on their own can be transpiled, as they run without any fuel limit.
+    let cases = (0..)
+        .map(Case::random)
+        .filter(|case| {
+            let outcome = Engine::Vm.run(case, FUZZ_FUEL);
+            !matches!(outcome.end, Err(Error::OutOfFuel { .. }))
+        })
+        .take(8);
+    for (idx, case) in cases.enumerate() {
+        let binary = compile(&format!("generated{}", idx), &case.program);
+        check(&binary, &case.program, &case.inputs);
+    }
+}
@@ -0,0 +1,112 @@
+//! Drives machines one instruction or one input at a time, checking the states they stop in.
+
+use std::time::Duration;
+
+use async_std::sync;
+use async_std::task;
+
+use aoc_2019::intcode::{self, Error, InputPolicy, State, Vm};
+
+/// Adds the two inputs it reads, outputs the sum, then halts.
+const SUM: &str = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+
+#[test]
+fn steps() {
+    let mut vm = Vm::new(intcode::parse(SUM));
+    vm.push_input(3);
+    vm.push_input(4);
+    let states: Vec<_> = (0..6)
+        .map(|_| vm.step().expect("invalid program"))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            State::Running,
+            State::Running,
+            State::Running,
+            State::Output(7),
+            State::Halted,
+            State::Halted,
+        ]
+    );
+    assert_eq!(vm.steps(), 6);
+    assert_eq!(vm.ip(), 10);
+}
+
+#[test]
+fn resumes_after_input() {
+    let mut vm = Vm::new(intcode::parse(SUM));
+    assert_eq!(vm.run(), Ok(State::NeedsInput));
+    assert_eq!(vm.ip(), 0);
+
+    vm.push_input(3);
+    assert_eq!(vm.run(), Ok(State::NeedsInput));
+    assert_eq!(vm.ip(), 2);
+    // a blocked input instruction isn't executed.
+    assert_eq!(vm.step(), Ok(State::NeedsInput));
+    assert_eq!(vm.ip(), 2);
+
+    vm.push_input(4);
+    assert_eq!(vm.run_until_io(), Ok(State::Output(7)));
+    assert_eq!(vm.run(), Ok(State::Halted));
+    assert!(vm.take_outputs().is_empty());
+}
+
+#[test]
+fn buffers_outputs() {
+    let mut vm = Vm::new(intcode::parse("104,1,104,2,3,0,104,3,99"));
+    assert_eq!(vm.run(), Ok(State::NeedsInput));
+    assert_eq!(vm.take_outputs(), [1, 2]);
+    vm.push_input(0);
+    assert_eq!(vm.run(), Ok(State::Halted));
+    assert_eq!(vm.pop_output(), Some(3));
+    assert_eq!(vm.pop_output(), None);
+}
+
+#[test]
+fn closed_channels() {
+    // outputs every input it reads, forever.
+    let program = intcode::parse("3,7,4,7,1105,1,0,0");
+    for policy in [
+        InputPolicy::Block,
+        InputPolicy::Default(0),
+        InputPolicy::Replay,
+    ]
+    .iter()
+    {
+        let (sender, input) = sync::channel(1);
+        let (output, receiver) = sync::channel(1);
+        let mut vm = Vm::new(program.clone());
+        task::block_on(async {
+            sender.send(5).await;
+            drop(sender);
+            assert_eq!(
+                vm.run_async(&input, &output, policy).await,
+                Err(Error::InputStarved { address: 0 }),
+                "{:?}",
+                policy
+            );
+            assert_eq!(receiver.recv().await, Some(5));
+        });
+    }
+}
+
+#[test]
+fn replays_across_runs() {
+    let (sender, input) = sync::channel(1);
+    let (output, receiver) = sync::channel(1);
+    let mut vm = Vm::new(intcode::parse(SUM));
+    task::block_on(async {
+        sender.send(3).await;
+        let policy = InputPolicy::Timeout(Duration::from_millis(10));
+        assert_eq!(
+            vm.run_async(&input, &output, &policy).await,
+            Ok(State::NeedsInput)
+        );
+        assert_eq!(
+            vm.run_async(&input, &output, &InputPolicy::Replay).await,
+            Ok(State::Halted)
+        );
+        assert_eq!(receiver.recv().await, Some(6));
+    });
+}
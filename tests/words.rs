@@ -0,0 +1,44 @@
+//! Runs programs with every word type, checking how each one handles values beyond the range of an `isize`.
+
+use aoc_2019::intcode::{self, BigInt, Checked, Error, Interpreter, Word};
+
+/// Raises its input to the fourth power, then outputs it.
+const POWER: &str = "3,0,2,0,0,0,2,0,0,0,4,0,99";
+
+/// Squares its input, then jumps to the result.
+const JUMP: &str = "3,100,2,100,100,100,105,1,100,99";
+
+fn run<W: Word>(program: &str, input: W) -> Result<Vec<W>, Error> {
+    let mut outputs = Vec::new();
+    Interpreter::new(intcode::parse(program))
+        .run_with(Some(input), |value| outputs.push(value))
+        .map(|_| outputs)
+}
+
+#[test]
+fn wrapping() {
+    assert_eq!(run(POWER, 1isize << 20), Ok(vec![0]));
+    assert_eq!(run(POWER, 1i128 << 20), Ok(vec![1i128 << 80]));
+}
+
+#[test]
+fn checked() {
+    assert_eq!(
+        run(POWER, Checked(1i64 << 20)),
+        Err(Error::Overflow { address: 6 })
+    );
+    assert_eq!(
+        run(POWER, Checked(1i64 << 15)),
+        Ok(vec![Checked(1i64 << 60)])
+    );
+}
+
+#[test]
+fn big_integers() {
+    let input = BigInt::from(1) << 40;
+    assert_eq!(run(POWER, input), Ok(vec![BigInt::from(1) << 160]));
+    assert_eq!(
+        run(JUMP, BigInt::from(1) << 40),
+        Err(Error::Overflow { address: 6 })
+    );
+}